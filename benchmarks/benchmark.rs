@@ -1,14 +1,154 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use project_voxels_v2::engine_loop::run;
+use std::f32::consts::TAU;
 
-fn _main2() {
-    std::env::set_var("RUST_LOG", "error");
-    pretty_env_logger::init();
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{ivec3, uvec3, vec3, Quat, UVec3, Vec3};
+use project_voxels_v2::{
+    app::Engine,
+    camera_path::{CameraPath, CameraPathPlayer},
+    entity::{Entities, ModelId, Transform, VoxelModel},
+    headless::HeadlessRenderer,
+    physics::{self, Aabb},
+    world::{Material, VoxelWorld},
+    FrameTimer, FIXED_TIMESTEP,
+};
 
-    pollster::block_on(run());
+/// Rolling hills of stone under a layer of grass, `size` voxels wide and deep.
+fn generate_world(size: u32) -> VoxelWorld {
+    let height = 64;
+    let mut world = VoxelWorld::new(uvec3(size, height, size), vec3(-(size as f32) / 2.0, -16.0, -(size as f32) / 2.0));
+    let stone = world.add_material(Material::opaque(vec3(0.55, 0.55, 0.5), 0.9));
+    let grass = world.add_material(Material::opaque(vec3(0.3, 0.6, 0.2), 0.8));
+
+    for z in 0..size as i32 {
+        for x in 0..size as i32 {
+            let hills = (x as f32 * 0.11).sin() * 6.0 + (z as f32 * 0.07).cos() * 8.0 + (x as f32 * 0.03 + z as f32 * 0.05).sin() * 10.0;
+            let surface = (24.0 + hills) as i32;
+            world.fill(ivec3(x, 0, z), ivec3(x + 1, surface - 1, z + 1), stone);
+            world.set(ivec3(x, surface - 1, z), grass);
+        }
+    }
+    world
+}
+
+/// Circles the world at a fixed height looking at its center, keyframed every half second.
+fn flyover_path(radius: f32, duration: f32) -> CameraPath {
+    let mut path = CameraPath::new();
+    let keyframes = (duration * 2.0) as usize;
+    for index in 0..=keyframes {
+        let time = index as f32 * duration / keyframes as f32;
+        let angle = time / duration * TAU;
+        let position = vec3(angle.cos() * radius, 28.0, angle.sin() * radius);
+        path.push(time, position, (vec3(0.0, 8.0, 0.0) - position).normalize());
+    }
+    path
+}
+
+fn world_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("world");
+    for size in [64, 128] {
+        group.bench_with_input(BenchmarkId::new("generate", size), &size, |b, &size| {
+            b.iter(|| generate_world(size))
+        });
+    }
+    group.finish();
 }
 
-pub fn criterion_benchmark(_: &mut Criterion) {}
+fn traversal_benchmarks(c: &mut Criterion) {
+    let world = generate_world(128);
+    let directions: Vec<Vec3> = (0..256)
+        .map(|index| {
+            let angle = index as f32 / 256.0 * TAU;
+            vec3(angle.cos(), -0.3, angle.sin())
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("traversal");
+    group.bench_function("raycast 256 rays", |b| {
+        b.iter(|| {
+            directions
+                .iter()
+                .filter_map(|&direction| physics::raycast(&world, vec3(0.0, 40.0, 0.0), direction, 200.0))
+                .count()
+        })
+    });
+    group.bench_function("sweep across hills", |b| {
+        let aabb = Aabb::from_feet(vec3(-60.0, 40.0, -60.0), vec3(0.6, 1.8, 0.6));
+        b.iter(|| physics::sweep(&world, aabb, vec3(120.0, -40.0, 120.0)))
+    });
+    group.finish();
+}
+
+fn entity_benchmarks(c: &mut Criterion) {
+    let mut entities = Entities::new();
+    let mut model = VoxelModel::new(UVec3::splat(8));
+    model.set(ivec3(0, 0, 0), 1);
+    let model = entities.add_model(model);
+    for index in 0..1000 {
+        let entity = entities.spawn();
+        let mut transform = Transform::new(vec3((index % 32) as f32 * 4.0, 0.0, (index / 32) as f32 * 4.0));
+        transform.rotation = Quat::from_rotation_y(index as f32);
+        entities.insert(entity, transform);
+        entities.insert::<ModelId>(entity, model);
+    }
+
+    c.bench_function("entities/instances 1000", |b| b.iter(|| entities.get_instances()));
+}
+
+fn camera_path_benchmarks(c: &mut Criterion) {
+    let path = flyover_path(48.0, 20.0);
+    c.bench_function("camera_path/sample", |b| {
+        let mut time = 0.0;
+        b.iter(|| {
+            time = (time + 0.016) % path.duration();
+            path.sample(time)
+        })
+    });
+}
+
+/// Renders the flyover at 60 steps per second through the real renderer, one frame per
+/// iteration. Skipped when the machine has no GPU adapter, including a software one.
+fn gpu_benchmarks(c: &mut Criterion) {
+    let mut engine = Engine::new();
+    engine.world = generate_world(128);
+    let Some(mut renderer) = pollster::block_on(HeadlessRenderer::new(&mut engine, 640, 360)) else {
+        eprintln!("No GPU adapter available, skipping the GPU benchmarks.");
+        return;
+    };
+
+    let mut player = CameraPathPlayer::new();
+    player.path = flyover_path(48.0, 20.0);
+    player.looping = true;
+    player.play();
+
+    let mut frame_timer = FrameTimer::new();
+    let mut group = c.benchmark_group("gpu");
+    group.sample_size(20);
+    group.bench_function("flyover 640x360", |b| {
+        b.iter(|| {
+            player.update(&mut engine.camera, FIXED_TIMESTEP as f32);
+            engine.camera.update(1.0);
+            renderer.render(&mut engine);
+            frame_timer.tick();
+        })
+    });
+    group.finish();
+
+    let stats = frame_timer.stats();
+    println!(
+        "gpu/flyover frame times: min {:.2} ms, average {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+        stats.min, stats.average, stats.p99, stats.max
+    );
+    if let Some(&gpu_time) = renderer.gpu_frame_history().back() {
+        println!("gpu/flyover last GPU frame time: {:.2} ms", gpu_time);
+    }
+}
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(
+    benches,
+    world_benchmarks,
+    traversal_benchmarks,
+    entity_benchmarks,
+    camera_path_benchmarks,
+    gpu_benchmarks
+);
 criterion_main!(benches);
@@ -0,0 +1,136 @@
+use egui::Context;
+use winit::event::WindowEvent;
+
+use crate::{
+    camera::Camera,
+    camera_path::{CameraPathPlayer, PathState},
+    debug_draw::DebugDraw,
+    entity::Entities,
+    input::{CursorMode, InputState},
+    replay::{InputEvent, RecordingStart},
+    settings::Settings,
+    world::VoxelWorld,
+    FrameTimer, FIXED_TIMESTEP,
+};
+
+/// Hooks for projects built on top of the engine, passed to `engine_loop::run`.
+///
+/// Every hook has an empty default, so an app only implements the ones it needs.
+pub trait App {
+    /// Called once before the renderer is created, the place to replace `engine.world`.
+    fn init(&mut self, _engine: &mut Engine) {}
+
+    /// Called every `FIXED_TIMESTEP` seconds, after the camera has moved.
+    fn fixed_update(&mut self, _engine: &mut Engine, _delta_time: f32) {}
+
+    /// Called once per frame before rendering, with the frame's duration in seconds.
+    fn update(&mut self, _engine: &mut Engine, _delta_time: f32) {}
+
+    /// Called while building the egui frame, after the engine's own settings window.
+    fn ui(&mut self, _ui: &Context) {}
+
+    /// Called once per frame for every input event applied since the previous frame, before the
+    /// fixed updates. Also called when replaying a recording, so input driven logic such as
+    /// editing belongs here.
+    fn input(&mut self, _engine: &mut Engine, _event: &InputEvent) {}
+
+    /// Called for every window event, after the engine has handled it. Not called when replaying
+    /// a recording, use `input` for anything a replay has to reproduce.
+    fn event(&mut self, _engine: &mut Engine, _event: &WindowEvent) {}
+}
+
+/// Engine state shared with the app hooks.
+pub struct Engine {
+    pub camera: Camera,
+    pub camera_path: CameraPathPlayer,
+    pub debug_draw: DebugDraw,
+    pub entities: Entities,
+    pub input: InputState,
+    /// Startup settings, edited and saved from the settings window.
+    pub settings: Settings,
+    /// Uploaded to the GPU once after `App::init`, later edits are not rendered.
+    pub world: VoxelWorld,
+    exit_requested: bool,
+    frame_timer: FrameTimer,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            camera: Camera::new(),
+            camera_path: CameraPathPlayer::new(),
+            debug_draw: DebugDraw::new(),
+            entities: Entities::new(),
+            input: InputState::new(),
+            settings: Settings::new(),
+            world: VoxelWorld::demo(),
+            exit_requested: false,
+            frame_timer: FrameTimer::new(),
+        }
+    }
+
+    /// Runs one frame of input, simulation and camera movement for the frame the frame timer
+    /// last counted. Shared by the engine loop and input replay, so both behave the same.
+    pub fn step(&mut self, app: &mut impl App) {
+        let delta_time = self.frame_timer.delta_time() as f32;
+        self.input.after_main_events();
+        for event in self.input.end_frame(self.frame_timer.delta_time()) {
+            app.input(self, &event);
+        }
+        if self.input.just_pressed("toggle_cursor_grab") {
+            let mouse = &mut self.input.mouse;
+            mouse.cursor_mode = match mouse.cursor_mode {
+                CursorMode::Grabbed => CursorMode::Free,
+                _ => CursorMode::Grabbed,
+            };
+        }
+
+        let path_playing = self.camera_path.state() == PathState::Playing;
+        for _ in 0..self.frame_timer.fixed_updates() {
+            if !path_playing {
+                self.camera.fixed_update(&self.input, &self.world, FIXED_TIMESTEP as f32);
+            }
+            app.fixed_update(self, FIXED_TIMESTEP as f32);
+        }
+        if !self.camera_path.update(&mut self.camera, delta_time) {
+            self.camera.look(&self.input, delta_time);
+        }
+        app.update(self, delta_time);
+        self.camera.update(self.frame_timer.alpha());
+    }
+
+    /// Starts recording input, along with the camera and frame timer state `replay` has to
+    /// start from.
+    pub fn start_recording(&mut self) {
+        let start = RecordingStart {
+            camera: self.camera.state(),
+            accumulator: self.frame_timer.accumulator(),
+            input: self.input.snapshot(),
+        };
+        self.input.start_recording(start);
+    }
+
+    /// Closes the window and leaves the event loop.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    pub fn frame_timer(&self) -> &FrameTimer {
+        &self.frame_timer
+    }
+
+    /// For driving frames without the engine loop, e.g. `FrameTimer::advance` before `step` in tests.
+    pub fn frame_timer_mut(&mut self) -> &mut FrameTimer {
+        &mut self.frame_timer
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,207 @@
 use glam::{vec3, Mat4, Quat, Vec3};
 
-use crate::input::InputState;
+use crate::{
+    input::InputState,
+    physics::{self, CharacterController, VoxelCollider},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Free flight through everything.
+    Fly,
+    /// First person on foot, colliding with the world.
+    Walk,
+    /// Circles a target point, for inspecting models.
+    Orbit,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 3] = [CameraMode::Fly, CameraMode::Walk, CameraMode::Orbit];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Fly => "Fly",
+            CameraMode::Walk => "Walk",
+            CameraMode::Orbit => "Orbit",
+        }
+    }
+}
+
+/// Tunables of the walk mode, speeds in units per second and heights in units.
+pub struct WalkSettings {
+    pub speed: f32,
+    pub sprint_multiplier: f32,
+    pub crouch_multiplier: f32,
+    pub jump_height: f32,
+    pub height: f32,
+    pub crouch_height: f32,
+    /// Distance from the top of the body down to the eyes.
+    pub eye_offset: f32,
+    pub gravity: f32,
+    pub step_height: f32,
+}
+
+impl WalkSettings {
+    pub fn new() -> Self {
+        Self {
+            speed: 4.0,
+            sprint_multiplier: 1.8,
+            crouch_multiplier: 0.4,
+            jump_height: 1.2,
+            height: 1.8,
+            crouch_height: 1.2,
+            eye_offset: 0.15,
+            gravity: 20.0,
+            step_height: 1.0,
+        }
+    }
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Orbit mode state, angles in radians.
+pub struct Orbit {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Orbit {
+    pub fn new() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 10.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+
+    /// Unit vector from the target towards the camera.
+    pub fn offset_direction(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        vec3(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+    }
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProjectionKind {
+    Perspective,
+    Orthographic,
+}
+
+impl ProjectionKind {
+    pub const ALL: [ProjectionKind; 2] = [ProjectionKind::Perspective, ProjectionKind::Orthographic];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectionKind::Perspective => "Perspective",
+            ProjectionKind::Orthographic => "Orthographic",
+        }
+    }
+}
+
+pub struct Projection {
+    pub kind: ProjectionKind,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    /// Vertical extent in units of the orthographic projection.
+    pub orthographic_height: f32,
+    pub near: f32,
+    pub far: f32,
+    aspect_ratio: f32,
+}
+
+impl Projection {
+    pub fn new() -> Self {
+        Self {
+            kind: ProjectionKind::Perspective,
+            fov: 45.0,
+            orthographic_height: 20.0,
+            near: 0.1,
+            far: 1000.0,
+            aspect_ratio: 1.0,
+        }
+    }
+
+    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect_ratio = width as f32 / height as f32;
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        let far = self.far.max(self.near + 0.01);
+        match self.kind {
+            ProjectionKind::Perspective => Mat4::perspective_rh(self.fov.to_radians(), self.aspect_ratio, self.near, far),
+            ProjectionKind::Orthographic => {
+                let half_height = self.orthographic_height / 2.0;
+                let half_width = half_height * self.aspect_ratio;
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, self.near, far)
+            }
+        }
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything the camera changes as it moves, so a replay can start from where a recording did.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraState {
+    pub mode: CameraMode,
+    pub position: Vec3,
+    pub previous_position: Vec3,
+    pub direction: Vec3,
+    pub crouching: bool,
+    /// Feet position of the walk mode body.
+    pub body_position: Vec3,
+    pub body_velocity: Vec3,
+    pub body_height: f32,
+    pub grounded: bool,
+    pub orbit_target: Vec3,
+    pub orbit_distance: f32,
+    pub orbit_yaw: f32,
+    pub orbit_pitch: f32,
+}
+
+/// Keeps the orbit camera from flipping over the poles.
+const MAX_ORBIT_PITCH: f32 = 1.55;
+/// Factor the orbit distance changes by per scrolled line.
+const ORBIT_ZOOM_PER_LINE: f32 = 0.9;
 
 pub struct Camera {
+    mode: CameraMode,
+    /// Fly mode speed in units per second.
+    pub fly_speed: f32,
+    pub walk: WalkSettings,
+    pub orbit: Orbit,
+    pub projection: Projection,
+    controller: CharacterController,
+    crouching: bool,
     direction: Vec3,
+    /// Eye position.
     position: Vec3,
-    projection_inverse: Mat4,
+    /// Position before the last fixed update, rendered frames interpolate from here.
+    previous_position: Vec3,
     uniform: CameraUniform,
     view_inverse: Mat4,
 }
@@ -13,54 +209,204 @@ pub struct Camera {
 impl Camera {
     pub fn new() -> Self {
         Self {
+            mode: CameraMode::Fly,
+            fly_speed: 3.0,
+            walk: WalkSettings::new(),
+            orbit: Orbit::new(),
+            projection: Projection::new(),
+            controller: CharacterController::new(Vec3::ZERO),
+            crouching: false,
             direction: vec3(0.0, 0.0, -1.0),
             position: vec3(0.0, 0.0, 3.0),
-            projection_inverse: Mat4::perspective_rh(std::f32::consts::PI / 4.0, 1.0, 1.0, 100.0).inverse(),
+            previous_position: vec3(0.0, 0.0, 3.0),
             uniform: CameraUniform::new(),
             view_inverse: Mat4::IDENTITY,
         }
     }
 
-    pub fn on_update(&mut self, input: &InputState) {
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Eye position after the last update.
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    /// Places the camera directly, without interpolating from its previous position.
+    pub fn set_pose(&mut self, position: Vec3, direction: Vec3) {
+        self.position = position;
+        self.previous_position = position;
+        self.direction = direction.normalize();
+        if self.mode == CameraMode::Walk {
+            self.controller.velocity = Vec3::ZERO;
+            self.controller.position = position - vec3(0.0, self.controller.size.y - self.walk.eye_offset, 0.0);
+        }
+    }
+
+    /// Switches modes, a walk starts with the feet below the current eye position.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Walk && self.mode != CameraMode::Walk {
+            self.crouching = false;
+            self.controller.velocity = Vec3::ZERO;
+            self.controller.position = self.position - vec3(0.0, self.walk.height - self.walk.eye_offset, 0.0);
+        }
+        if mode == CameraMode::Orbit && self.mode != CameraMode::Orbit {
+            let offset = -self.direction.normalize();
+            self.orbit.target = self.position - offset * self.orbit.distance;
+            self.orbit.pitch = offset.y.clamp(-1.0, 1.0).asin();
+            self.orbit.yaw = offset.x.atan2(offset.z);
+        }
+        self.mode = mode;
+    }
+
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            mode: self.mode,
+            position: self.position,
+            previous_position: self.previous_position,
+            direction: self.direction,
+            crouching: self.crouching,
+            body_position: self.controller.position,
+            body_velocity: self.controller.velocity,
+            body_height: self.controller.size.y,
+            grounded: self.controller.is_grounded(),
+            orbit_target: self.orbit.target,
+            orbit_distance: self.orbit.distance,
+            orbit_yaw: self.orbit.yaw,
+            orbit_pitch: self.orbit.pitch,
+        }
+    }
+
+    /// Puts the camera back into a state taken with `state`, settings are left alone.
+    pub fn set_state(&mut self, state: &CameraState) {
+        self.mode = state.mode;
+        self.position = state.position;
+        self.previous_position = state.previous_position;
+        self.direction = state.direction;
+        self.crouching = state.crouching;
+        self.controller.position = state.body_position;
+        self.controller.velocity = state.body_velocity;
+        self.controller.size.y = state.body_height;
+        self.controller.set_grounded(state.grounded);
+        self.orbit.target = state.orbit_target;
+        self.orbit.distance = state.orbit_distance;
+        self.orbit.yaw = state.orbit_yaw;
+        self.orbit.pitch = state.orbit_pitch;
+    }
+
+    pub fn fixed_update(&mut self, input: &InputState, world: &impl VoxelCollider, delta_time: f32) {
+        self.previous_position = self.position;
+        match self.mode {
+            CameraMode::Fly => self.fly(input, delta_time),
+            CameraMode::Walk => self.walk(input, world, delta_time),
+            CameraMode::Orbit => {}
+        }
+    }
+
+    fn fly(&mut self, input: &InputState, delta_time: f32) {
         let up_direction = Vec3::Y;
         let right_direction = self.direction.cross(up_direction);
+        let translation = self.fly_speed * delta_time;
 
-        let translation_speed = 0.05;
+        self.position += translation * self.direction * input.axis("move_backward", "move_forward");
+        self.position += translation * right_direction * input.axis("move_left", "move_right");
+        self.position += translation * up_direction * input.axis("move_down", "move_up");
+    }
 
-        if input.w {
-            self.position += translation_speed * self.direction;
-        } else if input.s {
-            self.position -= translation_speed * self.direction;
-        }
-        if input.a {
-            self.position -= translation_speed * right_direction;
-        } else if input.d {
-            self.position += translation_speed * right_direction;
+    fn walk(&mut self, input: &InputState, world: &impl VoxelCollider, delta_time: f32) {
+        let forward = vec3(self.direction.x, 0.0, self.direction.z).normalize_or_zero();
+        let right = forward.cross(Vec3::Y);
+
+        let wish_direction = forward * input.axis("move_backward", "move_forward") + right * input.axis("move_left", "move_right");
+
+        // Standing back up needs the headroom for the full height.
+        if input.pressed("crouch") {
+            self.crouching = true;
+        } else if self.crouching {
+            let standing = physics::Aabb::from_feet(
+                self.controller.position,
+                vec3(self.controller.size.x, self.walk.height, self.controller.size.z),
+            );
+            self.crouching = physics::intersects(world, standing);
         }
-        if input.q {
-            self.position -= translation_speed * up_direction;
-        } else if input.e {
-            self.position += translation_speed * up_direction;
+
+        let mut speed = self.walk.speed;
+        if self.crouching {
+            speed *= self.walk.crouch_multiplier;
+        } else if input.pressed("sprint") {
+            speed *= self.walk.sprint_multiplier;
         }
 
-        let rotation_speed = 1.0;
+        let height = if self.crouching {
+            self.walk.crouch_height
+        } else {
+            self.walk.height
+        };
+        self.controller.size.y = height;
+        self.controller.gravity = self.walk.gravity;
+        self.controller.step_height = self.walk.step_height;
+        if input.pressed("jump") {
+            self.controller.jump(self.walk.jump_height);
+        }
+        self.controller
+            .update(world, wish_direction.clamp_length_max(1.0) * speed, delta_time);
 
-        let mut pitch_delta = 0.0;
-        let mut yaw_delta = 0.0;
+        self.position = self.controller.position + vec3(0.0, height - self.walk.eye_offset, 0.0);
+    }
 
-        if input.right_mouse_button {
-            pitch_delta = input.delta_mouse_position.y * rotation_speed;
-            yaw_delta = input.delta_mouse_position.x * rotation_speed;
+    /// Mouse and stick look, applied every frame since the mouse delta already covers the whole frame.
+    pub fn look(&mut self, input: &InputState, delta_time: f32) {
+        if self.mode == CameraMode::Orbit {
+            self.orbit(input, delta_time);
+            return;
         }
 
+        let up_direction = Vec3::Y;
+        let right_direction = self.direction.cross(up_direction);
+
+        let look = input.look(delta_time);
+        let pitch_delta = look.y;
+        let yaw_delta = look.x;
+
         let rotation = Quat::from_axis_angle(right_direction, -pitch_delta) * Quat::from_axis_angle(up_direction, -yaw_delta).normalize();
 
         self.direction = rotation * self.direction;
+    }
+
+    /// Looking rotates around the target, the pan action drags the target and scroll zooms.
+    fn orbit(&mut self, input: &InputState, delta_time: f32) {
+        let orbit = &mut self.orbit;
+        let delta = input.delta_mouse_position;
+        let look = input.look(delta_time);
+        orbit.yaw -= look.x;
+        orbit.pitch = (orbit.pitch + look.y).clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
+        orbit.distance = (orbit.distance * ORBIT_ZOOM_PER_LINE.powf(input.scroll_delta)).max(0.1);
 
-        self.view_inverse = Mat4::look_to_rh(self.position, self.direction, up_direction).inverse();
+        let offset = orbit.offset_direction();
+        let right = Vec3::Y.cross(offset).normalize();
+        let up = offset.cross(right);
+        if input.pressed("pan") {
+            orbit.target += (-right * delta.x + up * delta.y) * orbit.distance;
+        }
+
+        self.direction = -offset;
+        self.position = orbit.target + offset * orbit.distance;
+        // Orbiting is not simulated, so there is nothing to interpolate from.
+        self.previous_position = self.position;
+    }
+
+    /// Updates the uniform for a frame `alpha` of the way between the last two fixed updates.
+    pub fn update(&mut self, alpha: f32) {
+        let position = self.previous_position.lerp(self.position, alpha);
+        self.view_inverse = Mat4::look_to_rh(position, self.direction, Vec3::Y).inverse();
 
         self.uniform
-            .update_projections(self.position, self.projection_inverse, self.view_inverse);
+            .update_projections(position, self.projection.matrix().inverse(), self.view_inverse);
     }
 
     pub fn get_uniform(&self) -> CameraUniform {
@@ -68,6 +414,12 @@ impl Camera {
     }
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
@@ -75,6 +427,8 @@ pub struct CameraUniform {
     _padding1: f32,
     projection_inverse: [[f32; 4]; 4],
     view_inverse: [[f32; 4]; 4],
+    view_projection: [[f32; 4]; 4],
+    previous_view_projection: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
@@ -84,6 +438,8 @@ impl CameraUniform {
             _padding1: 0.0,
             projection_inverse: Mat4::IDENTITY.to_cols_array_2d(),
             view_inverse: Mat4::IDENTITY.to_cols_array_2d(),
+            view_projection: Mat4::IDENTITY.to_cols_array_2d(),
+            previous_view_projection: Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
 
@@ -91,5 +447,21 @@ impl CameraUniform {
         self.position = position.into();
         self.projection_inverse = projection_inverse.to_cols_array_2d();
         self.view_inverse = view_inverse.to_cols_array_2d();
+        self.view_projection = (projection_inverse.inverse() * view_inverse.inverse()).to_cols_array_2d();
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&self.view_projection)
+    }
+
+    /// View projection of the previously rendered frame, used to reproject temporal history.
+    pub fn set_previous_view_projection(&mut self, previous_view_projection: Mat4) {
+        self.previous_view_projection = previous_view_projection.to_cols_array_2d();
+    }
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
     }
 }
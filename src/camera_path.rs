@@ -0,0 +1,242 @@
+use std::{fs, io, path::Path};
+
+use glam::{Quat, Vec3};
+
+use crate::{camera::Camera, invalid_data};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Keyframe {
+    pub fn direction(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+}
+
+/// Timed camera poses, interpolated with a Catmull-Rom spline for positions and slerp for rotations.
+///
+/// Saved as plain text, one `time px py pz qx qy qz qw` keyframe per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self { keyframes: Vec::new() }
+    }
+
+    /// Appends a keyframe, which has to be later than the last one.
+    pub fn push(&mut self, time: f32, position: Vec3, direction: Vec3) {
+        if self.keyframes.last().is_some_and(|last| last.time >= time) {
+            return;
+        }
+        self.keyframes.push(Keyframe {
+            time,
+            position,
+            rotation: Quat::from_rotation_arc(Vec3::NEG_Z, direction.normalize()),
+        });
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |last| last.time)
+    }
+
+    /// Position and direction at `time`, clamped to the ends of the path.
+    pub fn sample(&self, time: f32) -> Option<(Vec3, Vec3)> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time)
+            .clamp(1, last.max(1));
+        let index = next - 1;
+        if last == 0 {
+            let keyframe = &self.keyframes[0];
+            return Some((keyframe.position, keyframe.direction()));
+        }
+
+        let (start, end) = (&self.keyframes[index], &self.keyframes[next]);
+        let t = ((time - start.time) / (end.time - start.time)).clamp(0.0, 1.0);
+        let before = &self.keyframes[index.saturating_sub(1)];
+        let after = &self.keyframes[(next + 1).min(last)];
+
+        let position = catmull_rom(before.position, start.position, end.position, after.position, t);
+        let rotation = start.rotation.slerp(end.rotation, t);
+        Some((position, rotation * Vec3::NEG_Z))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = String::from("# time px py pz qx qy qz qw\n");
+        for keyframe in &self.keyframes {
+            let (p, q) = (keyframe.position, keyframe.rotation);
+            contents.push_str(&format!(
+                "{} {} {} {} {} {} {} {}\n",
+                keyframe.time, p.x, p.y, p.z, q.x, q.y, q.z, q.w
+            ));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut keyframes = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<f32> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|error| invalid_data(format!("Line {}: {}", number + 1, error)))?;
+            let [time, px, py, pz, qx, qy, qz, qw] = values[..] else {
+                return Err(invalid_data(format!("Line {}: expected 8 values", number + 1)));
+            };
+            if values.iter().any(|value| !value.is_finite()) {
+                return Err(invalid_data(format!("Line {}: values have to be finite", number + 1)));
+            }
+            // Sampling divides by the time between keyframes, the same rule `push` enforces.
+            if keyframes.last().is_some_and(|last: &Keyframe| last.time >= time) {
+                return Err(invalid_data(format!(
+                    "Line {}: time has to be later than the previous keyframe",
+                    number + 1
+                )));
+            }
+            let rotation = Quat::from_xyzw(qx, qy, qz, qw);
+            if rotation.length_squared() <= f32::EPSILON {
+                return Err(invalid_data(format!("Line {}: rotation has no length", number + 1)));
+            }
+            // Saved rotations are already unit length, normalizing again would not round trip exactly.
+            let rotation = if rotation.is_normalized() { rotation } else { rotation.normalize() };
+            keyframes.push(Keyframe {
+                time,
+                position: Vec3::new(px, py, pz),
+                rotation,
+            });
+        }
+        Ok(Self { keyframes })
+    }
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Uniform Catmull-Rom spline through `p1` and `p2`.
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PathState {
+    Stopped,
+    Recording,
+    Playing,
+}
+
+/// Records the camera into a [`CameraPath`] and plays it back in place of the usual controls.
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    /// Where the path is saved to and loaded from.
+    pub file: String,
+    pub looping: bool,
+    /// Seconds between recorded keyframes.
+    pub record_interval: f32,
+    state: PathState,
+    time: f32,
+}
+
+impl CameraPathPlayer {
+    pub fn new() -> Self {
+        Self {
+            path: CameraPath::new(),
+            file: String::from("camera_path.txt"),
+            looping: false,
+            record_interval: 0.25,
+            state: PathState::Stopped,
+            time: 0.0,
+        }
+    }
+
+    pub fn state(&self) -> PathState {
+        self.state
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Starts a new recording, replacing the current path.
+    pub fn record(&mut self) {
+        self.path = CameraPath::new();
+        self.time = 0.0;
+        self.state = PathState::Recording;
+    }
+
+    pub fn play(&mut self) {
+        if self.path.keyframes().len() >= 2 {
+            self.time = 0.0;
+            self.state = PathState::Playing;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.state = PathState::Stopped;
+    }
+
+    /// Advances the recording or playback by `delta_time`. Returns true when playback moved the
+    /// camera, in which case it should not also respond to input.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: f32) -> bool {
+        match self.state {
+            PathState::Stopped => false,
+            PathState::Recording => {
+                let due = self
+                    .path
+                    .keyframes()
+                    .last()
+                    .is_none_or(|last| self.time - last.time >= self.record_interval);
+                if due {
+                    self.path.push(self.time, camera.position(), camera.direction());
+                }
+                self.time += delta_time;
+                false
+            }
+            PathState::Playing => {
+                self.time += delta_time;
+                let duration = self.path.duration();
+                if self.time > duration {
+                    if self.looping && duration > 0.0 {
+                        self.time %= duration;
+                    } else {
+                        self.time = duration;
+                        self.state = PathState::Stopped;
+                    }
+                }
+                if let Some((position, direction)) = self.path.sample(self.time) {
+                    camera.set_pose(position, direction);
+                }
+                true
+            }
+        }
+    }
+}
+
+impl Default for CameraPathPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,125 @@
+use glam::{vec3, Mat4, Vec3};
+
+const SPHERE_SEGMENTS: u32 = 24;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl DebugVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn description<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Immediate mode debug geometry. Shapes are collected during a frame, drawn as lines on top of
+/// the voxels and cleared once the frame is rendered.
+pub struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self { vertices: Vec::new() }
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec3) {
+        self.vertices.push(DebugVertex {
+            position: start.into(),
+            color: color.into(),
+        });
+        self.vertices.push(DebugVertex {
+            position: end.into(),
+            color: color.into(),
+        });
+    }
+
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+        let corner = |i: u32| {
+            vec3(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        self.box_edges(corner, color);
+    }
+
+    /// Three great circles around the center, one per axis plane.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        for segment in 0..SPHERE_SEGMENTS {
+            let a = segment as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let b = (segment + 1) as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (sin_a, cos_a) = a.sin_cos();
+            let (sin_b, cos_b) = b.sin_cos();
+
+            self.line(
+                center + vec3(cos_a, sin_a, 0.0) * radius,
+                center + vec3(cos_b, sin_b, 0.0) * radius,
+                color,
+            );
+            self.line(
+                center + vec3(cos_a, 0.0, sin_a) * radius,
+                center + vec3(cos_b, 0.0, sin_b) * radius,
+                color,
+            );
+            self.line(
+                center + vec3(0.0, cos_a, sin_a) * radius,
+                center + vec3(0.0, cos_b, sin_b) * radius,
+                color,
+            );
+        }
+    }
+
+    /// X, Y and Z axes in red, green and blue.
+    pub fn axes(&mut self, origin: Vec3, size: f32) {
+        self.line(origin, origin + Vec3::X * size, Vec3::X);
+        self.line(origin, origin + Vec3::Y * size, Vec3::Y);
+        self.line(origin, origin + Vec3::Z * size, Vec3::Z);
+    }
+
+    /// Outline of the volume a camera sees, given the inverse of its view projection.
+    pub fn frustum(&mut self, view_projection_inverse: Mat4, color: Vec3) {
+        let corner = |i: u32| {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let z = if i & 4 == 0 { 0.0 } else { 1.0 };
+            view_projection_inverse.project_point3(vec3(x, y, z))
+        };
+        self.box_edges(corner, color);
+    }
+
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Connects eight corners indexed by their x, y and z bits into the twelve edges of a box.
+    fn box_edges(&mut self, corner: impl Fn(u32) -> Vec3, color: Vec3) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
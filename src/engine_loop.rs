@@ -1,30 +1,60 @@
-use crate::{camera::Camera, gui::EguiRenderer, input::InputState, renderer::Renderer, FrameTimer, GpuContext};
+use crate::{
+    app::{App, Engine},
+    gui::EguiRenderer,
+    input::InputMap,
+    renderer::Renderer,
+    settings::Settings,
+    world::VoxelWorld,
+    GpuContext,
+};
 
 use winit::{
-    dpi::LogicalSize,
+    dpi::{LogicalSize, PhysicalSize},
     event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::EventLoopBuilder,
     keyboard::{KeyCode, PhysicalKey},
-    window::WindowBuilder,
+    window::{CursorGrabMode, Fullscreen, Window, WindowBuilder},
 };
 
-pub async fn run() {
+/// Input bindings loaded at startup, the defaults are used when the file does not exist.
+pub const INPUT_CONFIG: &str = "input.cfg";
+
+pub async fn run(mut app: impl App, settings: Settings) {
     let event_loop = EventLoopBuilder::new().build().unwrap();
     let window = WindowBuilder::new()
-        .with_inner_size(LogicalSize::new(800, 800))
+        .with_inner_size(LogicalSize::new(settings.window_width, settings.window_height))
+        .with_fullscreen(fullscreen(settings.fullscreen))
         .build(&event_loop)
         .unwrap();
 
-    let mut camera = Camera::new();
+    let mut engine = Engine::new();
+    if let Some(path) = &settings.world {
+        match VoxelWorld::load(path) {
+            Ok(world) => engine.world = world,
+            Err(error) => log::error!("Failed to load world {}: {}", path.display(), error),
+        }
+    }
+    engine.settings = settings;
+    match InputMap::load(INPUT_CONFIG) {
+        Ok(map) => engine.input.map = map,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => log::error!("Failed to load {}: {}", INPUT_CONFIG, error),
+    }
+    let window_size = window.inner_size();
+    engine.camera.projection.set_aspect_ratio(window_size.width, window_size.height);
+    engine.input.set_window_size(window_size.width, window_size.height);
+    app.init(&mut engine);
 
-    let mut context = GpuContext::new(&window).await;
-    let renderer = Renderer::new(&context);
+    let mut context = GpuContext::new(&window, engine.settings.present_mode).await;
+    let mut renderer = Renderer::new(&context, &engine.world);
+    renderer.set_render_scale(&context, engine.settings.render_scale);
+    let mut applied_settings = engine.settings.clone();
     let mut egui = EguiRenderer::new(&context.device, &window, context.surface_format);
 
-    let mut frame_timer = FrameTimer::new();
-    let mut input_handler = InputState::new();
-
     let window = &window;
+    let mut cursor_grabbed = false;
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = crate::gamepad::Gamepads::new();
     event_loop
         .run(move |event, elwt| match event {
             Event::WindowEvent { window_id, ref event } if window_id == window.id() => {
@@ -42,7 +72,7 @@ pub async fn run() {
                     } => {
                         elwt.exit();
                     }
-                    WindowEvent::RedrawRequested => match renderer.render(&camera, &context, &mut egui, window, frame_timer.delta_time()) {
+                    WindowEvent::RedrawRequested => match renderer.render(&mut engine, &context, &mut egui, window, |ui| app.ui(ui)) {
                         Ok(_) => {}
                         Err(wgpu::SurfaceError::Lost) => elwt.exit(),
                         Err(wgpu::SurfaceError::OutOfMemory) => elwt.exit(),
@@ -50,22 +80,81 @@ pub async fn run() {
                     },
                     WindowEvent::Resized(new_size) => {
                         window.request_redraw();
-                        context.resize_surface_config(new_size);
+                        resize(&mut context, &mut renderer, &mut engine, *new_size);
                     }
                     _ => (),
                 };
 
-                input_handler.handle_event(event);
+                engine.input.handle_event(event);
 
                 egui.handle_input(window, event);
+                app.event(&mut engine, event);
                 window.request_redraw();
             }
+            Event::DeviceEvent { ref event, .. } => engine.input.handle_device_event(event),
             Event::AboutToWait => {
-                input_handler.after_main_events();
-                camera.on_update(&input_handler);
+                #[cfg(feature = "gamepad")]
+                gamepads.update(&mut engine.input);
+                engine.frame_timer_mut().tick();
+                engine.step(&mut app);
+                if engine.settings != applied_settings {
+                    let settings = &engine.settings;
+                    if (settings.window_width, settings.window_height) != (applied_settings.window_width, applied_settings.window_height) {
+                        let size = LogicalSize::new(settings.window_width, settings.window_height);
+                        if let Some(new_size) = window.request_inner_size(size) {
+                            resize(&mut context, &mut renderer, &mut engine, new_size);
+                        }
+                    }
+                    let settings = &engine.settings;
+                    if settings.fullscreen != applied_settings.fullscreen {
+                        window.set_fullscreen(fullscreen(settings.fullscreen));
+                    }
+                    if settings.present_mode != applied_settings.present_mode {
+                        context.set_present_mode(settings.present_mode);
+                    }
+                    if settings.render_scale != applied_settings.render_scale {
+                        renderer.set_render_scale(&context, settings.render_scale);
+                    }
+                    applied_settings = engine.settings.clone();
+                }
+                if engine.input.cursor_grabbed() != cursor_grabbed {
+                    cursor_grabbed = engine.input.cursor_grabbed();
+                    set_cursor_grab(window, cursor_grabbed);
+                }
+
+                if engine.exit_requested() {
+                    elwt.exit();
+                }
             }
 
             _ => {}
         })
         .expect("Event loop failed.");
 }
+
+fn resize(context: &mut GpuContext, renderer: &mut Renderer, engine: &mut Engine, new_size: PhysicalSize<u32>) {
+    context.resize_surface_config(&new_size);
+    renderer.resize(context);
+    engine.camera.projection.set_aspect_ratio(new_size.width, new_size.height);
+    engine.input.set_window_size(new_size.width, new_size.height);
+}
+
+fn fullscreen(enabled: bool) -> Option<Fullscreen> {
+    enabled.then_some(Fullscreen::Borderless(None))
+}
+
+/// Hides the cursor and locks it in place, falling back to confining it to the window on
+/// platforms that can not lock it.
+fn set_cursor_grab(window: &Window, grabbed: bool) {
+    let result = if grabbed {
+        window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    };
+    if let Err(error) = result {
+        log::error!("Failed to change the cursor grab: {}", error);
+    }
+    window.set_cursor_visible(!grabbed);
+}
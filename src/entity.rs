@@ -0,0 +1,380 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use glam::{IVec3, Mat4, Quat, UVec3, Vec3};
+
+use crate::world::AIR;
+
+/// Handle to an entity, stays invalid once the entity is despawned even if its slot is reused.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            translation,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO)
+    }
+}
+
+/// A small voxel grid using the world's material ids, centered on the entity's [`Transform`].
+pub struct VoxelModel {
+    size: UVec3,
+    voxels: Vec<u32>,
+}
+
+impl VoxelModel {
+    pub fn new(size: UVec3) -> Self {
+        Self {
+            size,
+            voxels: vec![AIR; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    pub fn get(&self, position: IVec3) -> u32 {
+        match self.index(position) {
+            Some(index) => self.voxels[index],
+            None => AIR,
+        }
+    }
+
+    pub fn set(&mut self, position: IVec3, material: u32) {
+        if let Some(index) = self.index(position) {
+            self.voxels[index] = material;
+        }
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    fn index(&self, position: IVec3) -> Option<usize> {
+        if position.cmplt(IVec3::ZERO).any() || position.cmpge(self.size.as_ivec3()).any() {
+            return None;
+        }
+        let position = position.as_uvec3();
+        Some((position.x + position.y * self.size.x + position.z * self.size.x * self.size.y) as usize)
+    }
+}
+
+/// Component that makes the raytracer draw a model at the entity's [`Transform`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModelId(u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceUniform {
+    world_to_model: [[f32; 4]; 4],
+    size: [u32; 3],
+    /// Index of the model's first voxel in the combined model voxel buffer.
+    offset: u32,
+}
+
+trait ComponentStorage {
+    fn remove(&mut self, index: usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<C: 'static> ComponentStorage for Vec<Option<C>> {
+    fn remove(&mut self, index: usize) {
+        if let Some(slot) = self.get_mut(index) {
+            *slot = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Dynamic objects on top of the static world. Components are plain values of any type, stored
+/// per type and indexed by entity.
+pub struct Entities {
+    components: HashMap<TypeId, Box<dyn ComponentStorage>>,
+    free: Vec<u32>,
+    generations: Vec<u32>,
+    models: Vec<VoxelModel>,
+    /// Bumped whenever the model set changes, so the renderer knows when to upload it again.
+    model_revision: u32,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            components: HashMap::new(),
+            free: Vec::new(),
+            generations: Vec::new(),
+            models: Vec::new(),
+            model_revision: 0,
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => Entity {
+                index,
+                generation: self.generations[index as usize],
+            },
+            None => {
+                self.generations.push(0);
+                Entity {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        for storage in self.components.values_mut() {
+            storage.remove(entity.index as usize);
+        }
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    /// Adds a component to the entity, replacing the one of the same type it already had.
+    pub fn insert<C: 'static>(&mut self, entity: Entity, component: C) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        let storage = self.storage_mut::<C>();
+        let index = entity.index as usize;
+        if storage.len() <= index {
+            storage.resize_with(index + 1, || None);
+        }
+        storage[index] = Some(component);
+    }
+
+    pub fn remove<C: 'static>(&mut self, entity: Entity) -> Option<C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<C>().get_mut(entity.index as usize)?.take()
+    }
+
+    pub fn get<C: 'static>(&self, entity: Entity) -> Option<&C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage::<C>()?.get(entity.index as usize)?.as_ref()
+    }
+
+    pub fn get_mut<C: 'static>(&mut self, entity: Entity) -> Option<&mut C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<C>().get_mut(entity.index as usize)?.as_mut()
+    }
+
+    /// Every living entity with a component of type `C`.
+    pub fn query<C: 'static>(&self) -> impl Iterator<Item = (Entity, &C)> {
+        self.storage::<C>().into_iter().flat_map(move |storage| {
+            storage.iter().enumerate().filter_map(move |(index, component)| {
+                let entity = Entity {
+                    index: index as u32,
+                    generation: self.generations[index],
+                };
+                Some((entity, component.as_ref()?))
+            })
+        })
+    }
+
+    pub fn add_model(&mut self, model: VoxelModel) -> ModelId {
+        self.models.push(model);
+        self.model_revision += 1;
+        ModelId(self.models.len() as u32 - 1)
+    }
+
+    pub fn model_revision(&self) -> u32 {
+        self.model_revision
+    }
+
+    /// Voxels of every model back to back, in the order they were added.
+    pub fn model_voxels(&self) -> Vec<u32> {
+        self.models.iter().flat_map(|model| model.voxels.iter().copied()).collect()
+    }
+
+    /// One instance per entity that has both a [`ModelId`] and a [`Transform`]. Instances whose
+    /// transform can't be inverted, e.g. with a zero scale, are skipped since they have no volume.
+    pub fn get_instances(&self) -> Vec<InstanceUniform> {
+        let mut offsets = Vec::with_capacity(self.models.len());
+        let mut offset = 0;
+        for model in &self.models {
+            offsets.push(offset);
+            offset += model.voxels.len() as u32;
+        }
+
+        self.query::<ModelId>()
+            .filter_map(|(entity, model_id)| {
+                let transform = self.get::<Transform>(entity)?;
+                let model = &self.models[model_id.0 as usize];
+                let model_to_world = transform.matrix() * Mat4::from_translation(-model.size.as_vec3() / 2.0);
+                if model_to_world.determinant() == 0.0 {
+                    return None;
+                }
+                let world_to_model = model_to_world.inverse();
+                if !world_to_model.is_finite() {
+                    return None;
+                }
+                Some(InstanceUniform {
+                    world_to_model: world_to_model.to_cols_array_2d(),
+                    size: model.size.into(),
+                    offset: offsets[model_id.0 as usize],
+                })
+            })
+            .collect()
+    }
+
+    fn storage<C: 'static>(&self) -> Option<&Vec<Option<C>>> {
+        self.components.get(&TypeId::of::<C>())?.as_any().downcast_ref()
+    }
+
+    fn storage_mut<C: 'static>(&mut self) -> &mut Vec<Option<C>> {
+        self.components
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(Vec::<Option<C>>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Component storage has the wrong type.")
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{uvec3, vec3};
+
+    use super::*;
+
+    #[test]
+    fn despawned_slots_are_reused_with_a_new_generation() {
+        let mut entities = Entities::new();
+        let first = entities.spawn();
+        let second = entities.spawn();
+        entities.despawn(first);
+        let reused = entities.spawn();
+
+        assert_eq!(reused.index, first.index);
+        assert_ne!(reused, first);
+        assert!(!entities.is_alive(first));
+        assert!(entities.is_alive(second));
+        assert!(entities.is_alive(reused));
+        assert_eq!(entities.spawn().index, 2);
+    }
+
+    #[test]
+    fn stale_handles_do_not_touch_the_reused_slot() {
+        let mut entities = Entities::new();
+        let stale = entities.spawn();
+        entities.insert(stale, 1u32);
+        entities.despawn(stale);
+        let reused = entities.spawn();
+
+        assert_eq!(entities.get::<u32>(reused), None, "despawn clears the components");
+        entities.insert(reused, 2u32);
+        entities.insert(stale, 3u32);
+        assert_eq!(entities.get::<u32>(stale), None);
+        assert_eq!(entities.get_mut::<u32>(stale), None);
+        assert_eq!(entities.remove::<u32>(stale), None);
+        assert_eq!(entities.get::<u32>(reused), Some(&2));
+
+        entities.despawn(stale);
+        assert!(entities.is_alive(reused));
+    }
+
+    #[test]
+    fn query_skips_removed_components() {
+        let mut entities = Entities::new();
+        let first = entities.spawn();
+        let second = entities.spawn();
+        let third = entities.spawn();
+        entities.insert(first, 1u32);
+        entities.insert(second, 2u32);
+        entities.insert(third, 3u32);
+        assert_eq!(entities.remove::<u32>(second), Some(2));
+        entities.despawn(third);
+
+        let found: Vec<_> = entities.query::<u32>().map(|(entity, &value)| (entity, value)).collect();
+        assert_eq!(found, [(first, 1)]);
+        assert_eq!(entities.query::<f32>().count(), 0);
+    }
+
+    #[test]
+    fn instances_point_at_their_model_voxels() {
+        let mut entities = Entities::new();
+        let small = entities.add_model(VoxelModel::new(uvec3(1, 2, 3)));
+        let large = entities.add_model(VoxelModel::new(uvec3(4, 4, 4)));
+        for model in [large, small] {
+            let entity = entities.spawn();
+            entities.insert(entity, model);
+            entities.insert(entity, Transform::new(vec3(1.0, 2.0, 3.0)));
+        }
+        let without_transform = entities.spawn();
+        entities.insert(without_transform, small);
+
+        let instances = entities.get_instances();
+        assert_eq!(instances.len(), 2);
+        assert_eq!((instances[0].offset, instances[0].size), (6, [4, 4, 4]));
+        assert_eq!((instances[1].offset, instances[1].size), (0, [1, 2, 3]));
+        assert_eq!(entities.model_voxels().len(), 6 + 64);
+    }
+
+    #[test]
+    fn instances_with_a_zero_scale_are_skipped() {
+        let mut entities = Entities::new();
+        let model = entities.add_model(VoxelModel::new(uvec3(2, 2, 2)));
+        let entity = entities.spawn();
+        entities.insert(entity, model);
+        entities.insert(
+            entity,
+            Transform {
+                scale: vec3(1.0, 0.0, 1.0),
+                ..Transform::default()
+            },
+        );
+
+        assert!(entities.get_instances().is_empty());
+    }
+}
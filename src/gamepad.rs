@@ -0,0 +1,95 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::input::{GamepadAxis, GamepadButton, InputState};
+
+/// Polls connected gamepads and feeds them into the action map. Every gamepad drives the same
+/// actions.
+pub struct Gamepads {
+    /// Missing if the platform's gamepad backend failed to start.
+    gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(error) => {
+                log::error!("Failed to initialize gamepads: {}", error);
+                None
+            }
+        };
+        Self { gilrs }
+    }
+
+    /// Applies the events received since the previous call, call once per frame before
+    /// `InputState::after_main_events`.
+    pub fn update(&mut self, input: &mut InputState) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = map_button(button) {
+                        input.set_gamepad_button(button, true);
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = map_button(button) {
+                        input.set_gamepad_button(button, false);
+                    }
+                }
+                // Analog triggers are reported as buttons with a value.
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    input.set_gamepad_axis(GamepadAxis::LeftTrigger, value);
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    input.set_gamepad_axis(GamepadAxis::RightTrigger, value);
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = map_axis(axis) {
+                        input.set_gamepad_axis(axis, value);
+                    }
+                }
+                EventType::Disconnected => input.clear_gamepad(),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn map_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn map_axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
@@ -1,9 +1,24 @@
+use std::path::PathBuf;
+
 use egui_wgpu::{Renderer, ScreenDescriptor};
 use egui_winit::{egui::Context, State};
 use wgpu::SurfaceTexture;
 use winit::{event::WindowEvent, window::Window};
 
-use crate::GpuContext;
+use crate::{
+    app::Engine,
+    camera::{Camera, CameraMode, Projection, ProjectionKind},
+    camera_path::{CameraPath, CameraPathPlayer, PathState},
+    input::{CursorMode, GamepadSettings, MouseSettings},
+    renderer::profiler::GpuProfiler,
+    renderer::settings::{
+        AntiAliasing, AntiAliasingSettings, DebugSettings, FogSettings, PostProcessNode, RenderMode, RenderSettings, ResolutionSettings,
+        TonemapOperator, TonemapSettings, Upscaler,
+    },
+    replay::INPUT_RECORDING_FILE,
+    settings::{PresentMode, Settings},
+    FrameTimer, GpuContext,
+};
 
 // From: https://github.com/ejb004/egui-wgpu-demo/blob/master/src/gui.rs
 
@@ -86,7 +101,7 @@ impl EguiRenderer {
     }
 }
 
-pub fn gui(ui: &Context, frametime: u128) {
+pub fn gui(ui: &Context, engine: &mut Engine, settings: &mut RenderSettings, profiler: &GpuProfiler) {
     egui::Window::new("Egui")
         .default_open(true)
         .max_width(1000.0)
@@ -95,8 +110,447 @@ pub fn gui(ui: &Context, frametime: u128) {
         .resizable(true)
         .anchor(egui::Align2::LEFT_TOP, [0.0, 0.0])
         .show(ui, |ui| {
-            ui.label(format!("Frametime: {}ms", frametime));
+            frame_time(ui, engine.frame_timer());
 
             ui.end_row();
+
+            gpu_profiler(ui, profiler);
+            camera_settings(ui, &mut engine.camera);
+            mouse_settings(ui, &mut engine.input.mouse);
+            gamepad_settings(ui, &mut engine.input.gamepad);
+            input_recording_settings(ui, engine);
+            camera_path_settings(ui, &mut engine.camera_path);
+            resolution_settings(ui, &mut settings.resolution, settings.anti_aliasing.mode);
+            anti_aliasing_settings(ui, &mut settings.anti_aliasing);
+            fog_settings(ui, &mut settings.fog);
+            tonemap_settings(ui, &mut settings.tonemap);
+            post_process_settings(ui, &mut settings.post_process);
+            debug_settings(ui, &mut settings.debug);
+            startup_settings(ui, &mut engine.settings, &settings.resolution);
+        });
+}
+
+/// Window settings apply right away, the rest on the next start. Saving also stores the current
+/// render scale.
+fn startup_settings(ui: &mut egui::Ui, settings: &mut Settings, resolution: &ResolutionSettings) {
+    egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Window size");
+            ui.add(egui::DragValue::new(&mut settings.window_width).clamp_range(1..=16384));
+            ui.add(egui::DragValue::new(&mut settings.window_height).clamp_range(1..=16384));
+        });
+        ui.checkbox(&mut settings.fullscreen, "Fullscreen");
+        egui::ComboBox::from_label("Present mode")
+            .selected_text(settings.present_mode.name())
+            .show_ui(ui, |ui| {
+                for option in PresentMode::ALL {
+                    ui.selectable_value(&mut settings.present_mode, option, option.name());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("Log level");
+            ui.text_edit_singleline(&mut settings.log_level);
+        });
+        ui.horizontal(|ui| {
+            ui.label("World");
+            let mut world = settings.world.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+            if ui.text_edit_singleline(&mut world).changed() {
+                settings.world = (!world.is_empty()).then(|| PathBuf::from(world));
+            }
+        });
+
+        // The dynamic scale only suits the moment it was picked in, so the last fixed scale is kept instead.
+        if resolution.dynamic {
+            ui.label(format!(
+                "Saves render scale {:.2}, dynamic resolution picks its own",
+                settings.render_scale
+            ));
+        }
+        if ui.button(format!("Save to {}", settings.file.display())).clicked() {
+            if !resolution.dynamic {
+                settings.render_scale = resolution.scale;
+            }
+            if let Err(error) = settings.save() {
+                log::error!("Failed to save settings {}: {}", settings.file.display(), error);
+            }
+        }
+    });
+}
+
+fn frame_time(ui: &mut egui::Ui, frame_timer: &FrameTimer) {
+    let stats = frame_timer.stats();
+    ui.label(format!(
+        "Frametime: {:.2}ms ({:.0} FPS)",
+        frame_timer.delta_time() * 1000.0,
+        stats.fps
+    ));
+    ui.label(format!(
+        "Min {:.2}ms  Avg {:.2}ms  Max {:.2}ms  P99 {:.2}ms",
+        stats.min, stats.average, stats.max, stats.p99
+    ));
+    history_graph(ui, frame_timer.history().iter().map(|seconds| *seconds as f32 * 1000.0));
+}
+
+fn camera_settings(ui: &mut egui::Ui, camera: &mut Camera) {
+    egui::CollapsingHeader::new("Camera").show(ui, |ui| {
+        let mut mode = camera.mode();
+        egui::ComboBox::from_label("Mode").selected_text(mode.name()).show_ui(ui, |ui| {
+            for option in CameraMode::ALL {
+                ui.selectable_value(&mut mode, option, option.name());
+            }
+        });
+        camera.set_mode(mode);
+        projection_settings(ui, &mut camera.projection);
+
+        match mode {
+            CameraMode::Fly => {
+                ui.add(egui::Slider::new(&mut camera.fly_speed, 0.5..=50.0).text("Speed"));
+            }
+            CameraMode::Walk => {
+                let walk = &mut camera.walk;
+                ui.add(egui::Slider::new(&mut walk.speed, 0.5..=20.0).text("Speed"));
+                ui.add(egui::Slider::new(&mut walk.sprint_multiplier, 1.0..=4.0).text("Sprint multiplier"));
+                ui.add(egui::Slider::new(&mut walk.crouch_multiplier, 0.1..=1.0).text("Crouch multiplier"));
+                ui.add(egui::Slider::new(&mut walk.jump_height, 0.0..=5.0).text("Jump height"));
+                ui.add(egui::Slider::new(&mut walk.height, 0.5..=3.0).text("Height"));
+                ui.add(egui::Slider::new(&mut walk.crouch_height, 0.5..=walk.height).text("Crouch height"));
+                ui.add(egui::Slider::new(&mut walk.eye_offset, 0.0..=0.5).text("Eye offset"));
+                ui.add(egui::Slider::new(&mut walk.gravity, 1.0..=50.0).text("Gravity"));
+                ui.add(egui::Slider::new(&mut walk.step_height, 0.0..=1.5).text("Step height"));
+            }
+            CameraMode::Orbit => {
+                let orbit = &mut camera.orbit;
+                ui.horizontal(|ui| {
+                    ui.label("Target");
+                    ui.add(egui::DragValue::new(&mut orbit.target.x).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut orbit.target.y).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut orbit.target.z).speed(0.1));
+                });
+                ui.add(
+                    egui::Slider::new(&mut orbit.distance, 0.1..=200.0)
+                        .logarithmic(true)
+                        .text("Distance"),
+                );
+            }
+        }
+    });
+}
+
+fn mouse_settings(ui: &mut egui::Ui, mouse: &mut MouseSettings) {
+    egui::CollapsingHeader::new("Mouse").show(ui, |ui| {
+        ui.add(
+            egui::Slider::new(&mut mouse.sensitivity, 0.0005..=0.01)
+                .logarithmic(true)
+                .text("Sensitivity"),
+        );
+        ui.checkbox(&mut mouse.invert_y, "Invert Y");
+        egui::ComboBox::from_label("Cursor")
+            .selected_text(mouse.cursor_mode.name())
+            .show_ui(ui, |ui| {
+                for option in CursorMode::ALL {
+                    ui.selectable_value(&mut mouse.cursor_mode, option, option.name());
+                }
+            });
+    });
+}
+
+fn gamepad_settings(ui: &mut egui::Ui, gamepad: &mut GamepadSettings) {
+    egui::CollapsingHeader::new("Gamepad").show(ui, |ui| {
+        ui.add(egui::Slider::new(&mut gamepad.stick_dead_zone, 0.0..=0.5).text("Stick dead zone"));
+        ui.add(egui::Slider::new(&mut gamepad.trigger_dead_zone, 0.0..=0.5).text("Trigger dead zone"));
+        ui.add(egui::Slider::new(&mut gamepad.look_speed, 0.5..=10.0).text("Look speed"));
+        ui.checkbox(&mut gamepad.invert_y, "Invert Y");
+    });
+}
+
+fn input_recording_settings(ui: &mut egui::Ui, engine: &mut Engine) {
+    egui::CollapsingHeader::new("Input recording").show(ui, |ui| match engine.input.recording() {
+        Some(recording) => {
+            ui.label(format!(
+                "Recording: {} frames, {:.1}s",
+                recording.frames().len(),
+                recording.duration()
+            ));
+            if ui.button("Stop and save").clicked() {
+                let recording = engine.input.stop_recording().expect("Recording was running.");
+                if let Err(error) = recording.save(INPUT_RECORDING_FILE) {
+                    log::error!("Failed to save input recording {}: {}", INPUT_RECORDING_FILE, error);
+                }
+            }
+        }
+        None => {
+            ui.label(format!("Saved to {}", INPUT_RECORDING_FILE));
+            if ui.button("Record").clicked() {
+                engine.start_recording();
+            }
+        }
+    });
+}
+
+fn camera_path_settings(ui: &mut egui::Ui, player: &mut CameraPathPlayer) {
+    egui::CollapsingHeader::new("Camera path").show(ui, |ui| {
+        let state = match player.state() {
+            PathState::Stopped => "Stopped",
+            PathState::Recording => "Recording",
+            PathState::Playing => "Playing",
+        };
+        ui.label(format!(
+            "{}: {:.1}s / {:.1}s, {} keyframes",
+            state,
+            player.time(),
+            player.path.duration(),
+            player.path.keyframes().len()
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Record").clicked() {
+                player.record();
+            }
+            if ui.button("Play").clicked() {
+                player.play();
+            }
+            if ui.button("Stop").clicked() {
+                player.stop();
+            }
+        });
+        ui.checkbox(&mut player.looping, "Loop");
+        ui.add(egui::Slider::new(&mut player.record_interval, 0.05..=2.0).text("Record interval"));
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut player.file);
+            if ui.button("Save").clicked() {
+                if let Err(error) = player.path.save(&player.file) {
+                    log::error!("Failed to save camera path {}: {}", player.file, error);
+                }
+            }
+            if ui.button("Load").clicked() {
+                match CameraPath::load(&player.file) {
+                    Ok(path) => {
+                        player.stop();
+                        player.path = path;
+                    }
+                    Err(error) => log::error!("Failed to load camera path {}: {}", player.file, error),
+                }
+            }
+        });
+    });
+}
+
+fn projection_settings(ui: &mut egui::Ui, projection: &mut Projection) {
+    egui::ComboBox::from_label("Projection")
+        .selected_text(projection.kind.name())
+        .show_ui(ui, |ui| {
+            for kind in ProjectionKind::ALL {
+                ui.selectable_value(&mut projection.kind, kind, kind.name());
+            }
+        });
+    match projection.kind {
+        ProjectionKind::Perspective => {
+            ui.add(egui::Slider::new(&mut projection.fov, 10.0..=150.0).text("Field of view"));
+        }
+        ProjectionKind::Orthographic => {
+            ui.add(
+                egui::Slider::new(&mut projection.orthographic_height, 1.0..=200.0)
+                    .logarithmic(true)
+                    .text("Height"),
+            );
+        }
+    }
+    ui.add(egui::Slider::new(&mut projection.near, 0.01..=10.0).logarithmic(true).text("Near"));
+    ui.add(egui::Slider::new(&mut projection.far, 10.0..=5000.0).logarithmic(true).text("Far"));
+}
+
+fn gpu_profiler(ui: &mut egui::Ui, profiler: &GpuProfiler) {
+    egui::CollapsingHeader::new("GPU timings").show(ui, |ui| {
+        if !profiler.is_supported() {
+            ui.label("Timestamp queries are not supported by this adapter.");
+            return;
+        }
+
+        let history = profiler.frame_history();
+        if let Some(latest) = history.back() {
+            ui.label(format!("GPU frame: {:.2}ms", latest));
+        }
+        history_graph(ui, history.iter().copied());
+
+        egui::Grid::new("Pass timings").striped(true).show(ui, |ui| {
+            for timing in profiler.pass_timings() {
+                ui.label(timing.label);
+                ui.label(format!("{:.3}ms", timing.average));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Line graph of recent samples, scaled so the largest one fills the height.
+fn history_graph(ui: &mut egui::Ui, samples: impl ExactSizeIterator<Item = f32> + Clone) {
+    let size = egui::vec2(ui.available_width(), 60.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let count = samples.len();
+    let max = samples.clone().fold(0.0f32, f32::max);
+    if count < 2 || max <= 0.0 {
+        return;
+    }
+
+    let points = samples
+        .enumerate()
+        .map(|(index, sample)| {
+            egui::pos2(
+                rect.left() + rect.width() * index as f32 / (count - 1) as f32,
+                rect.bottom() - rect.height() * sample / max,
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, ui.visuals().text_color())));
+    painter.text(
+        rect.left_top(),
+        egui::Align2::LEFT_TOP,
+        format!("{:.2}ms", max),
+        egui::FontId::monospace(10.0),
+        ui.visuals().weak_text_color(),
+    );
+}
+
+fn debug_settings(ui: &mut egui::Ui, debug: &mut DebugSettings) {
+    egui::CollapsingHeader::new("Debug").show(ui, |ui| {
+        egui::ComboBox::from_label("Render mode")
+            .selected_text(debug.render_mode.name())
+            .show_ui(ui, |ui| {
+                for mode in RenderMode::ALL {
+                    ui.selectable_value(&mut debug.render_mode, mode, mode.name());
+                }
+            });
+        ui.checkbox(&mut debug.world_bounds, "World bounds");
+        ui.checkbox(&mut debug.axes, "Axes");
+        ui.checkbox(&mut debug.sun_direction, "Sun direction");
+        ui.checkbox(&mut debug.freeze_frustum, "Freeze frustum");
+    });
+}
+
+fn post_process_settings(ui: &mut egui::Ui, nodes: &mut [PostProcessNode]) {
+    egui::CollapsingHeader::new("Post processing").show(ui, |ui| {
+        let mut swap = None;
+        let count = nodes.len();
+        for (index, node) in nodes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut node.enabled, node.effect.name());
+                if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                    swap = Some((index - 1, index));
+                }
+                if ui.add_enabled(index + 1 < count, egui::Button::new("⬇")).clicked() {
+                    swap = Some((index, index + 1));
+                }
+            });
+            ui.add_enabled_ui(node.enabled, |ui| {
+                ui.indent(node.effect.name(), |ui| {
+                    for (value, parameter) in node.values.iter_mut().zip(node.effect.parameters()) {
+                        ui.add(egui::Slider::new(value, parameter.min..=parameter.max).text(parameter.name));
+                    }
+                });
+            });
+        }
+        if let Some((a, b)) = swap {
+            nodes.swap(a, b);
+        }
+    });
+}
+
+fn tonemap_settings(ui: &mut egui::Ui, tonemap: &mut TonemapSettings) {
+    egui::CollapsingHeader::new("Tonemapping").show(ui, |ui| {
+        egui::ComboBox::from_label("Operator")
+            .selected_text(tonemap.operator.name())
+            .show_ui(ui, |ui| {
+                for operator in TonemapOperator::ALL {
+                    ui.selectable_value(&mut tonemap.operator, operator, operator.name());
+                }
+            });
+        ui.checkbox(&mut tonemap.auto_exposure, "Auto exposure");
+        let exposure_label = if tonemap.auto_exposure {
+            "Compensation (EV)"
+        } else {
+            "Exposure (EV)"
+        };
+        ui.add(egui::Slider::new(&mut tonemap.exposure, -8.0..=8.0).text(exposure_label));
+        ui.add_enabled_ui(tonemap.auto_exposure, |ui| {
+            ui.add(egui::Slider::new(&mut tonemap.adaptation_speed, 0.1..=10.0).text("Adaptation speed"));
+            ui.add(egui::Slider::new(&mut tonemap.min_log_luminance, -16.0..=0.0).text("Min log luminance"));
+            ui.add(egui::Slider::new(&mut tonemap.max_log_luminance, 0.0..=16.0).text("Max log luminance"));
+        });
+    });
+}
+
+fn resolution_settings(ui: &mut egui::Ui, resolution: &mut ResolutionSettings, anti_aliasing: AntiAliasing) {
+    egui::CollapsingHeader::new("Resolution").show(ui, |ui| {
+        ui.add_enabled(
+            !resolution.dynamic,
+            egui::Slider::new(&mut resolution.scale, 0.25..=1.0).text("Render scale"),
+        );
+        // Temporal anti-aliasing resolves straight to the window resolution, so it replaces the upscaler.
+        let temporal_aa = anti_aliasing == AntiAliasing::Temporal;
+        ui.add_enabled_ui(!temporal_aa, |ui| {
+            let selected = if temporal_aa {
+                Upscaler::Temporal.name()
+            } else {
+                resolution.upscaler.name()
+            };
+            egui::ComboBox::from_label("Upscaler").selected_text(selected).show_ui(ui, |ui| {
+                for upscaler in Upscaler::ALL {
+                    ui.selectable_value(&mut resolution.upscaler, upscaler, upscaler.name());
+                }
+            });
+        });
+        if temporal_aa && resolution.upscaler != Upscaler::Temporal {
+            ui.label(format!("Temporal anti-aliasing upscales instead of {}", resolution.upscaler.name()));
+        }
+        ui.checkbox(&mut resolution.dynamic, "Dynamic resolution");
+        ui.add_enabled_ui(resolution.dynamic, |ui| {
+            ui.add(egui::Slider::new(&mut resolution.target_frame_time, 4.0..=50.0).text("Frame time budget (ms)"));
+            ui.add(egui::Slider::new(&mut resolution.min_scale, 0.25..=1.0).text("Minimum scale"));
+        });
+    });
+}
+
+fn anti_aliasing_settings(ui: &mut egui::Ui, anti_aliasing: &mut AntiAliasingSettings) {
+    egui::CollapsingHeader::new("Anti-aliasing").show(ui, |ui| {
+        egui::ComboBox::from_label("Mode")
+            .selected_text(anti_aliasing.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in AntiAliasing::ALL {
+                    ui.selectable_value(&mut anti_aliasing.mode, mode, mode.name());
+                }
+            });
+        match anti_aliasing.mode {
+            AntiAliasing::Temporal => {
+                ui.add(egui::Slider::new(&mut anti_aliasing.temporal_blend, 0.01..=1.0).text("Current frame weight"));
+            }
+            AntiAliasing::Supersampling => {
+                ui.add(egui::Slider::new(&mut anti_aliasing.samples, 1..=16).text("Samples per pixel"));
+            }
+            AntiAliasing::None => {}
+        }
+    });
+}
+
+fn fog_settings(ui: &mut egui::Ui, fog: &mut FogSettings) {
+    egui::CollapsingHeader::new("Fog").show(ui, |ui| {
+        ui.checkbox(&mut fog.enabled, "Enabled");
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgb(&mut fog.color);
+            ui.label("Color");
+        });
+        ui.add(egui::Slider::new(&mut fog.density, 0.0..=0.5).logarithmic(true).text("Density"));
+        ui.add(egui::Slider::new(&mut fog.height, -16.0..=16.0).text("Height"));
+        ui.add(egui::Slider::new(&mut fog.height_falloff, 0.0..=2.0).text("Height falloff"));
+        ui.add(egui::Slider::new(&mut fog.anisotropy, -0.95..=0.95).text("Anisotropy"));
+
+        ui.separator();
+        ui.checkbox(&mut fog.volumetric, "Volumetric light shafts");
+        ui.add_enabled_ui(fog.volumetric, |ui| {
+            ui.add(egui::Slider::new(&mut fog.volumetric_steps, 1..=64).text("Steps"));
+            ui.add(egui::Slider::new(&mut fog.volumetric_strength, 0.0..=4.0).text("Strength"));
         });
+    });
 }
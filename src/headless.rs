@@ -0,0 +1,50 @@
+use std::collections::VecDeque;
+
+use crate::{app::Engine, renderer::Renderer, GpuContext};
+
+/// Renders the engine into a texture instead of a window, for benchmarks and tests.
+pub struct HeadlessRenderer {
+    context: GpuContext<'static>,
+    renderer: Renderer,
+    target: wgpu::TextureView,
+}
+
+impl HeadlessRenderer {
+    /// Uploads `engine.world` like the windowed loop does after `App::init`. `None` if there is no
+    /// GPU adapter, so callers can skip instead of failing.
+    pub async fn new(engine: &mut Engine, width: u32, height: u32) -> Option<Self> {
+        let context = GpuContext::headless(width, height).await?;
+        let renderer = Renderer::new(&context, &engine.world);
+        engine.camera.projection.set_aspect_ratio(width, height);
+
+        let target = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless target texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: context.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let target = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Some(Self { context, renderer, target })
+    }
+
+    /// Renders one frame and blocks until the GPU has finished it. Leaves the engine's frame timer
+    /// alone, so rendering can be mixed with `replay` or other code driving the timer.
+    pub fn render(&mut self, engine: &mut Engine) {
+        self.renderer.render_offscreen(engine, &self.context, &self.target);
+        self.context.device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// GPU time of recent frames in milliseconds, empty without timestamp query support.
+    pub fn gpu_frame_history(&self) -> &VecDeque<f32> {
+        self.renderer.profiler().frame_history()
+    }
+}
@@ -1,89 +1,880 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::Path,
+    sync::OnceLock,
+};
+
 use glam::{vec2, Vec2};
 use winit::{
-    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     keyboard::{KeyCode, PhysicalKey},
 };
 
-use crate::ScreenSpace;
+use crate::{
+    invalid_data,
+    replay::{InputEvent, InputRecording, RecordingStart},
+    ScreenSpace,
+};
+
+/// Converts touchpad pixel scrolling into mouse wheel lines.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Every key, so any key can be saved and loaded by its `KeyCode` name.
+const KEYS: &[KeyCode] = &[
+    KeyCode::Backquote,
+    KeyCode::Backslash,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Comma,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Equal,
+    KeyCode::IntlBackslash,
+    KeyCode::IntlRo,
+    KeyCode::IntlYen,
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Quote,
+    KeyCode::Semicolon,
+    KeyCode::Slash,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Backspace,
+    KeyCode::CapsLock,
+    KeyCode::ContextMenu,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::Enter,
+    KeyCode::SuperLeft,
+    KeyCode::SuperRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Convert,
+    KeyCode::KanaMode,
+    KeyCode::Lang1,
+    KeyCode::Lang2,
+    KeyCode::Lang3,
+    KeyCode::Lang4,
+    KeyCode::Lang5,
+    KeyCode::NonConvert,
+    KeyCode::Delete,
+    KeyCode::End,
+    KeyCode::Help,
+    KeyCode::Home,
+    KeyCode::Insert,
+    KeyCode::PageDown,
+    KeyCode::PageUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ArrowUp,
+    KeyCode::NumLock,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadBackspace,
+    KeyCode::NumpadClear,
+    KeyCode::NumpadClearEntry,
+    KeyCode::NumpadComma,
+    KeyCode::NumpadDecimal,
+    KeyCode::NumpadDivide,
+    KeyCode::NumpadEnter,
+    KeyCode::NumpadEqual,
+    KeyCode::NumpadHash,
+    KeyCode::NumpadMemoryAdd,
+    KeyCode::NumpadMemoryClear,
+    KeyCode::NumpadMemoryRecall,
+    KeyCode::NumpadMemoryStore,
+    KeyCode::NumpadMemorySubtract,
+    KeyCode::NumpadMultiply,
+    KeyCode::NumpadParenLeft,
+    KeyCode::NumpadParenRight,
+    KeyCode::NumpadStar,
+    KeyCode::NumpadSubtract,
+    KeyCode::Escape,
+    KeyCode::Fn,
+    KeyCode::FnLock,
+    KeyCode::PrintScreen,
+    KeyCode::ScrollLock,
+    KeyCode::Pause,
+    KeyCode::BrowserBack,
+    KeyCode::BrowserFavorites,
+    KeyCode::BrowserForward,
+    KeyCode::BrowserHome,
+    KeyCode::BrowserRefresh,
+    KeyCode::BrowserSearch,
+    KeyCode::BrowserStop,
+    KeyCode::Eject,
+    KeyCode::LaunchApp1,
+    KeyCode::LaunchApp2,
+    KeyCode::LaunchMail,
+    KeyCode::MediaPlayPause,
+    KeyCode::MediaSelect,
+    KeyCode::MediaStop,
+    KeyCode::MediaTrackNext,
+    KeyCode::MediaTrackPrevious,
+    KeyCode::Power,
+    KeyCode::Sleep,
+    KeyCode::AudioVolumeDown,
+    KeyCode::AudioVolumeMute,
+    KeyCode::AudioVolumeUp,
+    KeyCode::WakeUp,
+    KeyCode::Meta,
+    KeyCode::Hyper,
+    KeyCode::Turbo,
+    KeyCode::Abort,
+    KeyCode::Resume,
+    KeyCode::Suspend,
+    KeyCode::Again,
+    KeyCode::Copy,
+    KeyCode::Cut,
+    KeyCode::Find,
+    KeyCode::Open,
+    KeyCode::Paste,
+    KeyCode::Props,
+    KeyCode::Select,
+    KeyCode::Undo,
+    KeyCode::Hiragana,
+    KeyCode::Katakana,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::F25,
+    KeyCode::F26,
+    KeyCode::F27,
+    KeyCode::F28,
+    KeyCode::F29,
+    KeyCode::F30,
+    KeyCode::F31,
+    KeyCode::F32,
+    KeyCode::F33,
+    KeyCode::F34,
+    KeyCode::F35,
+];
+
+/// Axis values below this count as not held when an axis drives a digital query like `pressed`.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Gamepad buttons by position, so bindings work the same across controller brands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 14] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Sticks go from -1 to 1 with y pointing up, triggers from 0 to 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    /// The other axis of the same stick, dead zones are applied to both together.
+    fn stick_partner(&self) -> Option<GamepadAxis> {
+        match self {
+            GamepadAxis::LeftStickX => Some(GamepadAxis::LeftStickY),
+            GamepadAxis::LeftStickY => Some(GamepadAxis::LeftStickX),
+            GamepadAxis::RightStickX => Some(GamepadAxis::RightStickY),
+            GamepadAxis::RightStickY => Some(GamepadAxis::RightStickX),
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => None,
+        }
+    }
+}
+
+/// Something an action can be bound to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+    /// One direction of an axis, `true` for the positive one.
+    GamepadAxis(GamepadAxis, bool),
+}
+
+impl Binding {
+    /// Name used in input configs, e.g. `KeyW`, `MouseRight`, `GamepadSouth` or `GamepadLeftStickY+`.
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+            Binding::GamepadButton(button) => format!("Gamepad{:?}", button),
+            Binding::GamepadAxis(axis, positive) => format!("Gamepad{:?}{}", axis, if *positive { '+' } else { '-' }),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(number) = name.strip_prefix("MouseOther(").and_then(|rest| rest.strip_suffix(')')) {
+            return Some(Binding::Mouse(MouseButton::Other(number.parse().ok()?)));
+        }
+        static BINDINGS: OnceLock<HashMap<String, Binding>> = OnceLock::new();
+        let bindings = BINDINGS.get_or_init(|| {
+            let mouse = [
+                MouseButton::Left,
+                MouseButton::Right,
+                MouseButton::Middle,
+                MouseButton::Back,
+                MouseButton::Forward,
+            ];
+            let axes = GamepadAxis::ALL
+                .into_iter()
+                .flat_map(|axis| [Binding::GamepadAxis(axis, true), Binding::GamepadAxis(axis, false)]);
+            KEYS.iter()
+                .copied()
+                .map(Binding::Key)
+                .chain(mouse.into_iter().map(Binding::Mouse))
+                .chain(GamepadButton::ALL.into_iter().map(Binding::GamepadButton))
+                .chain(axes)
+                .map(|binding| (binding.name(), binding))
+                .collect()
+        });
+        bindings.get(name).copied()
+    }
+}
+
+/// Action names mapped to the bindings that trigger them. An action is held while any of its
+/// bindings is.
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+}
+
+impl InputMap {
+    /// The default bindings the camera modes are written against.
+    pub fn new() -> Self {
+        let mut map = Self::empty();
+        map.bind("move_forward", Binding::Key(KeyCode::KeyW));
+        map.bind("move_forward", Binding::GamepadAxis(GamepadAxis::LeftStickY, true));
+        map.bind("move_backward", Binding::Key(KeyCode::KeyS));
+        map.bind("move_backward", Binding::GamepadAxis(GamepadAxis::LeftStickY, false));
+        map.bind("move_left", Binding::Key(KeyCode::KeyA));
+        map.bind("move_left", Binding::GamepadAxis(GamepadAxis::LeftStickX, false));
+        map.bind("move_right", Binding::Key(KeyCode::KeyD));
+        map.bind("move_right", Binding::GamepadAxis(GamepadAxis::LeftStickX, true));
+        map.bind("move_up", Binding::Key(KeyCode::KeyE));
+        map.bind("move_up", Binding::GamepadAxis(GamepadAxis::RightTrigger, true));
+        map.bind("move_down", Binding::Key(KeyCode::KeyQ));
+        map.bind("move_down", Binding::GamepadAxis(GamepadAxis::LeftTrigger, true));
+        map.bind("jump", Binding::Key(KeyCode::Space));
+        map.bind("jump", Binding::GamepadButton(GamepadButton::South));
+        map.bind("sprint", Binding::Key(KeyCode::ShiftLeft));
+        map.bind("sprint", Binding::GamepadButton(GamepadButton::LeftStick));
+        map.bind("crouch", Binding::Key(KeyCode::ControlLeft));
+        map.bind("crouch", Binding::GamepadButton(GamepadButton::East));
+        map.bind("look", Binding::Mouse(MouseButton::Right));
+        map.bind("look_left", Binding::GamepadAxis(GamepadAxis::RightStickX, false));
+        map.bind("look_right", Binding::GamepadAxis(GamepadAxis::RightStickX, true));
+        map.bind("look_up", Binding::GamepadAxis(GamepadAxis::RightStickY, true));
+        map.bind("look_down", Binding::GamepadAxis(GamepadAxis::RightStickY, false));
+        map.bind("pan", Binding::Mouse(MouseButton::Middle));
+        map.bind("toggle_cursor_grab", Binding::Key(KeyCode::Tab));
+        map
+    }
+
+    pub fn empty() -> Self {
+        Self { actions: BTreeMap::new() }
+    }
+
+    /// Adds a binding to the action, creating the action if needed.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes every binding of the action.
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        self.actions.iter().map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+    }
+
+    /// Writes one `action = Binding Binding` line per action.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = String::from("# action = bindings, e.g. KeyW, MouseRight, GamepadSouth or GamepadLeftStickY+\n");
+        for (action, bindings) in self.actions() {
+            let names: Vec<String> = bindings.iter().map(Binding::name).collect();
+            contents.push_str(&format!("{} = {}\n", action, names.join(" ")));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut map = Self::empty();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((action, bindings)) = line.split_once('=') else {
+                return Err(invalid_data(format!("Line {}: expected `action = bindings`", number + 1)));
+            };
+            let action = action.trim();
+            map.actions.entry(action.to_string()).or_default();
+            for name in bindings.split_whitespace() {
+                let binding = Binding::parse(name).ok_or_else(|| invalid_data(format!("Line {}: unknown binding {}", number + 1, name)))?;
+                map.bind(action, binding);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How the cursor behaves over the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CursorMode {
+    /// Visible and free, the look action has to be held to turn the camera.
+    Free,
+    /// Hidden and locked in place while the look action is held.
+    GrabWhileLooking,
+    /// Always hidden and locked, every mouse movement turns the camera.
+    Grabbed,
+}
+
+impl CursorMode {
+    pub const ALL: [CursorMode; 3] = [CursorMode::Free, CursorMode::GrabWhileLooking, CursorMode::Grabbed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CursorMode::Free => "Free",
+            CursorMode::GrabWhileLooking => "Grab while looking",
+            CursorMode::Grabbed => "Grabbed",
+        }
+    }
+}
+
+pub struct MouseSettings {
+    /// Radians turned per pixel of mouse movement.
+    pub sensitivity: f32,
+    pub invert_y: bool,
+    pub cursor_mode: CursorMode,
+}
+
+impl MouseSettings {
+    pub fn new() -> Self {
+        Self {
+            sensitivity: 0.0025,
+            invert_y: false,
+            cursor_mode: CursorMode::GrabWhileLooking,
+        }
+    }
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct GamepadSettings {
+    /// Stick deflection ignored around the center, as a fraction of the full range.
+    pub stick_dead_zone: f32,
+    pub trigger_dead_zone: f32,
+    /// Radians per second the camera turns with the look stick fully deflected.
+    pub look_speed: f32,
+    pub invert_y: bool,
+}
+
+impl GamepadSettings {
+    pub fn new() -> Self {
+        Self {
+            stick_dead_zone: 0.15,
+            trigger_dead_zone: 0.05,
+            look_speed: 3.0,
+            invert_y: false,
+        }
+    }
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rescales `value` so the range past the dead zone covers 0 to 1 again.
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value <= dead_zone {
+        0.0
+    } else {
+        ((value - dead_zone) / (1.0 - dead_zone).max(f32::EPSILON)).min(1.0)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct ActionState {
+    /// Strongest of the action's bindings, 1 for held buttons and the deflection for axes.
+    value: f32,
+    down: bool,
+    previous: bool,
+}
 
 pub struct InputState {
-    pub w: bool,
-    pub s: bool,
-    pub a: bool,
-    pub d: bool,
-    pub q: bool,
-    pub e: bool,
-    pub right_mouse_button: bool,
+    pub map: InputMap,
+    pub mouse: MouseSettings,
+    pub gamepad: GamepadSettings,
+    actions: HashMap<String, ActionState>,
+    /// Bindings currently held down.
+    held: HashSet<Binding>,
+    /// Bindings pressed since the previous frame, so a tap shorter than a frame is not lost.
+    tapped: HashSet<Binding>,
+    recording: Option<InputRecording>,
+    /// Events applied since the previous frame, handed to the app and the recording by `end_frame`.
+    frame_events: Vec<InputEvent>,
+    /// Raw gamepad axis values, before dead zones.
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    /// Mouse movement since the previous frame in the same units as `mouse_position`, measured
+    /// from raw device motion so it keeps working while the cursor is grabbed.
     pub delta_mouse_position: Vec2,
+    /// Mouse camera rotation in radians since the previous frame, with the mouse settings applied.
+    mouse_look: Vec2,
+    /// Raw mouse motion in pixels accumulated since the previous frame.
+    motion: Vec2,
+    /// Mouse wheel lines scrolled since the previous frame, positive away from the user.
+    pub scroll_delta: f32,
+    scroll: f32,
+    /// Cursor position from -1 to 1 across the window, y pointing down.
     pub mouse_position: Vec2,
-    previous_mouse_position: Vec2,
+    /// Cursor position in physical pixels.
+    cursor_position: Vec2,
+    window_size: Vec2,
 }
 
 impl InputState {
     pub fn new() -> Self {
         Self {
-            w: false,
-            s: false,
-            a: false,
-            d: false,
-            q: false,
-            e: false,
-            right_mouse_button: false,
+            map: InputMap::new(),
+            mouse: MouseSettings::new(),
+            gamepad: GamepadSettings::new(),
+            actions: HashMap::new(),
+            held: HashSet::new(),
+            tapped: HashSet::new(),
+            recording: None,
+            frame_events: Vec::new(),
+            gamepad_axes: HashMap::new(),
             delta_mouse_position: Vec2::ZERO,
+            mouse_look: Vec2::ZERO,
+            motion: Vec2::ZERO,
+            scroll_delta: 0.0,
+            scroll: 0.0,
             mouse_position: Vec2::ZERO,
-            previous_mouse_position: Vec2::ZERO,
+            cursor_position: Vec2::ZERO,
+            window_size: vec2(800.0, 800.0),
+        }
+    }
+
+    /// Size of the window in physical pixels, kept up to date by `WindowEvent::Resized`.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.window_size = vec2(width.max(1) as f32, height.max(1) as f32);
+    }
+
+    /// Whether mouse movement should turn the camera this frame.
+    pub fn looking(&self) -> bool {
+        self.mouse.cursor_mode == CursorMode::Grabbed || self.pressed("look")
+    }
+
+    /// Whether the cursor should currently be hidden and locked in place.
+    pub fn cursor_grabbed(&self) -> bool {
+        match self.mouse.cursor_mode {
+            CursorMode::Free => false,
+            CursorMode::GrabWhileLooking => self.pressed("look"),
+            CursorMode::Grabbed => true,
+        }
+    }
+
+    /// Whether the action is held this frame.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.action(action).down
+    }
+
+    /// Whether the action went down this frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        let state = self.action(action);
+        state.down && !state.previous
+    }
+
+    /// Whether the action was let go of this frame.
+    pub fn just_released(&self, action: &str) -> bool {
+        let state = self.action(action);
+        !state.down && state.previous
+    }
+
+    /// How far the action is held, from 0 to 1. Buttons and keys are either 0 or 1.
+    pub fn value(&self, action: &str) -> f32 {
+        self.action(action).value
+    }
+
+    /// From -1 with only `negative` held to 1 with only `positive` held, analog for gamepad axes.
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    /// Camera rotation in radians for a frame of `delta_time` seconds, x turning right and y
+    /// turning down. Combines mouse look with the look stick.
+    pub fn look(&self, delta_time: f32) -> Vec2 {
+        let mouse = if self.looking() { self.mouse_look } else { Vec2::ZERO };
+        let mut stick = vec2(self.axis("look_left", "look_right"), self.axis("look_up", "look_down"));
+        if self.gamepad.invert_y {
+            stick.y = -stick.y;
         }
+        mouse + stick * self.gamepad.look_speed * delta_time
     }
 
+    fn action(&self, action: &str) -> ActionState {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+
+    /// Applies the window events the input state cares about, ignoring the rest.
     pub fn handle_event(&mut self, event: &WindowEvent) {
-        match event {
+        let event = match event {
             WindowEvent::KeyboardInput {
-                event: KeyEvent { state, physical_key, .. },
+                event:
+                    KeyEvent {
+                        state,
+                        physical_key: PhysicalKey::Code(key),
+                        ..
+                    },
                 ..
-            } => {
-                let is_pressed = *state == ElementState::Pressed;
-                match physical_key {
-                    PhysicalKey::Code(KeyCode::KeyW) => {
-                        self.w = is_pressed;
-                    }
-                    PhysicalKey::Code(KeyCode::KeyS) => {
-                        self.s = is_pressed;
-                    }
-                    PhysicalKey::Code(KeyCode::KeyA) => {
-                        self.a = is_pressed;
-                    }
-                    PhysicalKey::Code(KeyCode::KeyD) => {
-                        self.d = is_pressed;
-                    }
-                    PhysicalKey::Code(KeyCode::KeyQ) => {
-                        self.q = is_pressed;
-                    }
-                    PhysicalKey::Code(KeyCode::KeyE) => {
-                        self.e = is_pressed;
-                    }
-                    _ => {}
-                }
+            } => InputEvent::button(Binding::Key(*key), *state == ElementState::Pressed),
+            WindowEvent::CursorMoved { position, .. } => InputEvent::CursorMoved(vec2(position.x as f32, position.y as f32)),
+            WindowEvent::Resized(size) => InputEvent::Resized(size.width, size.height),
+            WindowEvent::MouseInput { button, state, .. } => InputEvent::button(Binding::Mouse(*button), *state == ElementState::Pressed),
+            WindowEvent::MouseWheel { delta, .. } => InputEvent::Scroll(match delta {
+                MouseScrollDelta::LineDelta(_, y) => *y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+            }),
+            _ => return,
+        };
+        self.apply(event);
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            self.apply(InputEvent::MouseMotion(vec2(*x as f32, *y as f32)));
+        }
+    }
+
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, is_pressed: bool) {
+        self.apply(InputEvent::button(Binding::GamepadButton(button), is_pressed));
+    }
+
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.apply(InputEvent::GamepadAxis(axis, value));
+    }
+
+    /// Releases every gamepad button and centers every axis, e.g. when a gamepad disconnects.
+    pub fn clear_gamepad(&mut self) {
+        self.apply(InputEvent::GamepadDisconnected);
+    }
+
+    /// Applies an event from any source, queueing it for `App::input` and the recording.
+    pub fn apply(&mut self, event: InputEvent) {
+        self.frame_events.push(event);
+        match event {
+            InputEvent::Press(binding) => {
+                self.held.insert(binding);
+                self.tapped.insert(binding);
+            }
+            InputEvent::Release(binding) => {
+                self.held.remove(&binding);
+            }
+            InputEvent::GamepadAxis(axis, value) => {
+                self.gamepad_axes.insert(axis, value);
             }
-            WindowEvent::CursorMoved { position, .. } => {
-                self.mouse_position = vec2(position.x as f32, position.y as f32).to_screen_space(&800.0, &800.0);
+            InputEvent::GamepadDisconnected => {
+                self.held.retain(|binding| !matches!(binding, Binding::GamepadButton(_)));
+                self.gamepad_axes.clear();
             }
-            WindowEvent::MouseInput { button, state, .. } => match button {
-                MouseButton::Left => {
-                    if *state == ElementState::Pressed {
-                        self.right_mouse_button = true;
-                    }
-                    if *state == ElementState::Released {
-                        self.right_mouse_button = false;
-                    }
+            InputEvent::CursorMoved(position) => {
+                self.cursor_position = position;
+                self.mouse_position = position.to_screen_space(&self.window_size.x, &self.window_size.y);
+            }
+            InputEvent::Resized(width, height) => self.set_window_size(width, height),
+            InputEvent::MouseMotion(delta) => self.motion += delta,
+            InputEvent::Scroll(lines) => self.scroll += lines,
+        }
+    }
+
+    /// Starts capturing every applied event, grouped into frames by `end_frame`. Use
+    /// `Engine::start_recording` to fill in `start` from the running engine.
+    pub fn start_recording(&mut self, start: RecordingStart) {
+        self.recording = Some(InputRecording::new(start));
+        self.frame_events.clear();
+    }
+
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recording.take()
+    }
+
+    pub fn recording(&self) -> Option<&InputRecording> {
+        self.recording.as_ref()
+    }
+
+    /// Events that recreate the current window size, cursor position, held bindings and gamepad
+    /// axes when passed to `restore`.
+    pub fn snapshot(&self) -> Vec<InputEvent> {
+        let mut events = vec![
+            InputEvent::Resized(self.window_size.x as u32, self.window_size.y as u32),
+            InputEvent::CursorMoved(self.cursor_position),
+        ];
+        let mut held: Vec<Binding> = self.held.iter().copied().collect();
+        held.sort_by_key(|binding| binding.name());
+        events.extend(held.into_iter().map(InputEvent::Press));
+        events.extend(
+            GamepadAxis::ALL
+                .into_iter()
+                .filter_map(|axis| Some(InputEvent::GamepadAxis(axis, *self.gamepad_axes.get(&axis)?))),
+        );
+        events
+    }
+
+    /// Releases everything, applies `events` and settles the action states on them, so the next
+    /// frame starts from the input state a `snapshot` was taken in.
+    pub fn restore(&mut self, events: &[InputEvent]) {
+        self.held.clear();
+        self.tapped.clear();
+        self.gamepad_axes.clear();
+        self.motion = Vec2::ZERO;
+        self.scroll = 0.0;
+        for event in events {
+            self.apply(*event);
+        }
+        self.tapped.clear();
+        self.after_main_events();
+        self.frame_events.clear();
+    }
+
+    /// Closes a frame that lasted `delta_time` seconds, returning the events applied during it and
+    /// adding them to the recording if one is running.
+    pub(crate) fn end_frame(&mut self, delta_time: f64) -> Vec<InputEvent> {
+        let events = std::mem::take(&mut self.frame_events);
+        if let Some(recording) = &mut self.recording {
+            recording.push(delta_time, events.clone());
+        }
+        events
+    }
+
+    /// Axis value with the dead zone applied, sticks use a radial dead zone across both axes.
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+        match axis.stick_partner() {
+            Some(partner) => {
+                let partner = self.gamepad_axes.get(&partner).copied().unwrap_or(0.0);
+                let length = vec2(value, partner).length();
+                if length == 0.0 {
+                    return 0.0;
                 }
-                _ => {}
-            },
-            _ => {}
+                value / length * apply_dead_zone(length, self.gamepad.stick_dead_zone)
+            }
+            None => apply_dead_zone(value, self.gamepad.trigger_dead_zone),
+        }
+    }
+
+    fn binding_value(&self, binding: &Binding) -> f32 {
+        match binding {
+            Binding::GamepadAxis(axis, positive) => {
+                let value = self.gamepad_axis(*axis);
+                if *positive {
+                    value.max(0.0)
+                } else {
+                    (-value).max(0.0)
+                }
+            }
+            _ => (self.held.contains(binding) || self.tapped.contains(binding)) as i32 as f32,
         }
     }
 
     pub fn after_main_events(&mut self) {
-        self.delta_mouse_position = self.mouse_position - self.previous_mouse_position;
-        self.previous_mouse_position = self.mouse_position;
+        self.delta_mouse_position = self.motion * 2.0 / self.window_size;
+        self.mouse_look = self.motion * self.mouse.sensitivity;
+        if self.mouse.invert_y {
+            self.mouse_look.y = -self.mouse_look.y;
+        }
+        self.motion = Vec2::ZERO;
+        self.scroll_delta = self.scroll;
+        self.scroll = 0.0;
+
+        let mut actions = std::mem::take(&mut self.actions);
+        for state in actions.values_mut() {
+            state.previous = state.down;
+            state.down = false;
+            state.value = 0.0;
+        }
+        for (action, bindings) in self.map.actions() {
+            let value = bindings.iter().map(|binding| self.binding_value(binding)).fold(0.0, f32::max);
+            if value > 0.0 {
+                let state = actions.entry(action.to_string()).or_default();
+                state.value = value;
+                state.down = value >= AXIS_PRESS_THRESHOLD;
+            }
+        }
+        self.actions = actions;
+        self.tapped.clear();
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_binding_name_parses_back() {
+        let keys = KEYS.iter().map(|&key| Binding::Key(key));
+        let buttons = GamepadButton::ALL.into_iter().map(Binding::GamepadButton);
+        let axes = GamepadAxis::ALL
+            .into_iter()
+            .flat_map(|axis| [Binding::GamepadAxis(axis, true), Binding::GamepadAxis(axis, false)]);
+        let mouse = [
+            MouseButton::Left,
+            MouseButton::Right,
+            MouseButton::Middle,
+            MouseButton::Back,
+            MouseButton::Forward,
+            MouseButton::Other(0),
+            MouseButton::Other(u16::MAX),
+        ]
+        .into_iter()
+        .map(Binding::Mouse);
+
+        for binding in keys.chain(buttons).chain(axes).chain(mouse) {
+            assert_eq!(Binding::parse(&binding.name()), Some(binding), "{}", binding.name());
+        }
+    }
+
+    #[test]
+    fn binding_names_are_unique() {
+        let mut names = HashSet::new();
+        for key in KEYS {
+            assert!(names.insert(Binding::Key(*key).name()), "{:?}", key);
+        }
     }
 }
@@ -1,24 +1,43 @@
-use std::time::Instant;
+use std::{collections::VecDeque, io, time::Instant};
 
 use glam::Vec2;
 use winit::{dpi::PhysicalSize, window::Window};
 
+use crate::settings::PresentMode;
+
+pub mod app;
 pub mod camera;
+pub mod camera_path;
+pub mod debug_draw;
 pub mod engine_loop;
+pub mod entity;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
 mod gui;
-mod input;
+pub mod headless;
+pub mod input;
+pub mod physics;
 mod renderer;
+pub mod replay;
+pub mod settings;
+pub mod world;
+
+pub use egui;
+pub use winit;
 
 pub struct GpuContext<'a> {
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface: wgpu::Surface<'a>,
+    /// Missing for headless contexts, which render into textures only.
+    surface: Option<wgpu::Surface<'a>>,
     surface_config: wgpu::SurfaceConfiguration,
     surface_format: wgpu::TextureFormat,
+    /// Present modes the surface supports, besides the automatic ones every surface supports.
+    present_modes: Vec<wgpu::PresentMode>,
 }
 
 impl<'a> GpuContext<'a> {
-    pub async fn new(window: &'a Window) -> Self {
+    pub async fn new(window: &'a Window, present_mode: PresentMode) -> Self {
         let instance_descriptor = wgpu::InstanceDescriptor::default();
         let instance = wgpu::Instance::new(instance_descriptor);
 
@@ -33,8 +52,10 @@ impl<'a> GpuContext<'a> {
             .await
             .expect("Failed to request adapter.");
 
+        // Timestamp queries are only used for profiling, so they are requested but not required.
         let device_descriptor = wgpu::DeviceDescriptor {
             label: Some("Device"),
+            required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
             ..Default::default()
         };
         let (device, queue) = adapter
@@ -57,50 +78,198 @@ impl<'a> GpuContext<'a> {
             format: surface_format,
             width: window_size.width,
             height: window_size.height,
-            present_mode: surface_capabilities.present_modes[0],
+            present_mode: wgpu::PresentMode::AutoVsync,
             alpha_mode: surface_capabilities.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
-        surface.configure(&device, &surface_config);
 
-        Self {
+        let mut context = Self {
             device,
             queue,
-            surface,
+            surface: Some(surface),
             surface_config,
             surface_format,
+            present_modes: surface_capabilities.present_modes,
+        };
+        context.set_present_mode(present_mode);
+        context
+    }
+
+    /// Reconfigures the surface, falling back to vsync if the mode is not supported.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        let mut mode = present_mode.to_wgpu();
+        if !matches!(mode, wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync) && !self.present_modes.contains(&mode) {
+            log::error!("Present mode {} is not supported, using vsync.", present_mode.name());
+            mode = wgpu::PresentMode::AutoVsync;
+        }
+        self.surface_config.present_mode = mode;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
         }
     }
 
+    /// Context without a window, rendering `width` by `height` frames. `None` if there is no adapter.
+    pub async fn headless(width: u32, height: u32) -> Option<GpuContext<'static>> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await?;
+
+        let device_descriptor = wgpu::DeviceDescriptor {
+            label: Some("Device"),
+            required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+            ..Default::default()
+        };
+        let (device, queue) = adapter.request_device(&device_descriptor, None).await.ok()?;
+
+        let surface_format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        Some(GpuContext {
+            device,
+            queue,
+            surface: None,
+            surface_config,
+            surface_format,
+            present_modes: Vec::new(),
+        })
+    }
+
     pub fn resize_surface_config(&mut self, new_size: &PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.surface_config);
+            }
         }
     }
 }
 
+/// Number of recent frames kept for the frame time statistics.
+const FRAME_HISTORY_LENGTH: usize = 240;
+/// Duration of one simulation update in seconds.
+pub const FIXED_TIMESTEP: f64 = 1.0 / 60.0;
+/// Caps the updates run in a single frame, so a long stall slows the simulation down instead of
+/// making every following frame even longer.
+const MAX_FIXED_UPDATES: u32 = 8;
+
 pub struct FrameTimer {
     time: Instant,
-    delta_time: u128,
+    delta_time: f64,
+    accumulator: f64,
+    history: VecDeque<f64>,
+}
+
+/// Frame time statistics over the recent history, in milliseconds.
+pub struct FrameStats {
+    pub min: f64,
+    pub average: f64,
+    pub max: f64,
+    pub p99: f64,
+    pub fps: f64,
 }
 
 impl FrameTimer {
     pub fn new() -> Self {
         FrameTimer {
             time: Instant::now(),
-            delta_time: 0,
+            delta_time: 0.0,
+            accumulator: 0.0,
+            history: VecDeque::with_capacity(FRAME_HISTORY_LENGTH),
         }
     }
 
-    pub fn delta_time(&mut self) -> u128 {
+    /// Measures the time since the previous tick, call once per frame.
+    pub fn tick(&mut self) -> f64 {
         let new_time = Instant::now();
-        self.delta_time = (new_time - self.time).as_millis();
+        let delta_time = (new_time - self.time).as_secs_f64();
         self.time = new_time;
+        self.advance(delta_time);
+        delta_time
+    }
+
+    /// Counts a frame of the given duration instead of a measured one, e.g. when replaying input.
+    pub fn advance(&mut self, delta_time: f64) {
+        self.delta_time = delta_time;
+        self.accumulator += delta_time;
+
+        if self.history.len() == FRAME_HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.delta_time);
+    }
+
+    /// Consumes the accumulated frame time in steps of `FIXED_TIMESTEP`, returning how many
+    /// updates to run this frame.
+    pub fn fixed_updates(&mut self) -> u32 {
+        let mut updates = 0;
+        while self.accumulator >= FIXED_TIMESTEP && updates < MAX_FIXED_UPDATES {
+            self.accumulator -= FIXED_TIMESTEP;
+            updates += 1;
+        }
+        if updates == MAX_FIXED_UPDATES {
+            self.accumulator = self.accumulator.min(FIXED_TIMESTEP);
+        }
+        updates
+    }
+
+    /// Frame time not yet consumed by fixed updates, in seconds.
+    pub fn accumulator(&self) -> f64 {
+        self.accumulator
+    }
+
+    /// Restores the leftover frame time, so a replay runs the same fixed updates as the recording.
+    pub fn set_accumulator(&mut self, accumulator: f64) {
+        self.accumulator = accumulator;
+    }
+
+    /// How far the current frame is between the last two fixed updates, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / FIXED_TIMESTEP) as f32
+    }
+
+    /// Duration of the last frame in seconds.
+    pub fn delta_time(&self) -> f64 {
         self.delta_time
     }
+
+    /// Recent frame durations in seconds, oldest first.
+    pub fn history(&self) -> &VecDeque<f64> {
+        &self.history
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        if self.history.is_empty() {
+            return FrameStats {
+                min: 0.0,
+                average: 0.0,
+                max: 0.0,
+                p99: 0.0,
+                fps: 0.0,
+            };
+        }
+
+        let mut sorted: Vec<f64> = self.history.iter().map(|seconds| seconds * 1000.0).collect();
+        sorted.sort_by(f64::total_cmp);
+        let average = sorted.iter().sum::<f64>() / sorted.len() as f64;
+        let p99_index = ((sorted.len() as f64 * 0.99).ceil() as usize).saturating_sub(1);
+        FrameStats {
+            min: sorted[0],
+            average,
+            max: sorted[sorted.len() - 1],
+            p99: sorted[p99_index],
+            fps: if average > 0.0 { 1000.0 / average } else { 0.0 },
+        }
+    }
 }
 
 impl Default for FrameTimer {
@@ -121,3 +290,85 @@ impl ScreenSpace for Vec2 {
         }
     }
 }
+
+/// Error for files that were read fine but hold something we can't load.
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn stats_of_an_empty_history_are_zero() {
+        let stats = FrameTimer::new().stats();
+
+        assert_eq!([stats.min, stats.average, stats.max, stats.p99, stats.fps], [0.0; 5]);
+    }
+
+    #[test]
+    fn stats_use_the_nearest_rank_for_the_p99() {
+        let mut timer = FrameTimer::new();
+        for milliseconds in (1..=100).rev() {
+            timer.advance(milliseconds as f64 / 1000.0);
+        }
+        let stats = timer.stats();
+
+        assert_close(stats.min, 1.0);
+        assert_close(stats.max, 100.0);
+        assert_close(stats.average, 50.5);
+        assert_close(stats.p99, 99.0);
+        assert_close(stats.fps, 1000.0 / 50.5);
+
+        let mut timer = FrameTimer::new();
+        timer.advance(0.004);
+        assert_close(timer.stats().p99, 4.0);
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_frames() {
+        let mut timer = FrameTimer::new();
+        for frame in 0..FRAME_HISTORY_LENGTH + 60 {
+            timer.advance(frame as f64);
+        }
+
+        assert_eq!(timer.history().len(), FRAME_HISTORY_LENGTH);
+        assert_eq!(timer.history().front(), Some(&60.0));
+        assert_eq!(timer.history().back(), Some(&(FRAME_HISTORY_LENGTH as f64 + 59.0)));
+        assert_eq!(timer.delta_time(), FRAME_HISTORY_LENGTH as f64 + 59.0);
+    }
+
+    #[test]
+    fn fixed_updates_consume_the_accumulator() {
+        let mut timer = FrameTimer::new();
+        timer.advance(FIXED_TIMESTEP * 2.5);
+
+        assert_eq!(timer.fixed_updates(), 2);
+        assert_close(timer.accumulator(), FIXED_TIMESTEP * 0.5);
+        assert!((timer.alpha() - 0.5).abs() < 1e-5);
+        assert_eq!(timer.fixed_updates(), 0);
+
+        timer.advance(FIXED_TIMESTEP * 0.75);
+        assert_eq!(timer.fixed_updates(), 1);
+        assert_close(timer.accumulator(), FIXED_TIMESTEP * 0.25);
+    }
+
+    #[test]
+    fn long_frames_are_clamped_to_the_maximum_updates() {
+        let mut timer = FrameTimer::new();
+        timer.advance(1.0);
+
+        assert_eq!(timer.fixed_updates(), MAX_FIXED_UPDATES);
+        assert_eq!(timer.accumulator(), FIXED_TIMESTEP);
+        assert!((0.0..=1.0).contains(&timer.alpha()), "{}", timer.alpha());
+
+        timer.advance(0.0);
+        assert_eq!(timer.fixed_updates(), 1);
+        assert!((0.0..=1.0).contains(&timer.alpha()), "{}", timer.alpha());
+    }
+}
@@ -1,8 +1,62 @@
-use project_voxels_v2::engine_loop::run;
+use glam::{ivec3, uvec3, vec3, Quat};
+use project_voxels_v2::{
+    app::{App, Engine},
+    engine_loop::run,
+    entity::{Entity, Transform, VoxelModel},
+    settings::{Settings, USAGE},
+    world::Material,
+};
+
+/// The demo world with a spinning crate entity.
+#[derive(Default)]
+struct Viewer {
+    prop: Option<Entity>,
+}
+
+impl App for Viewer {
+    fn init(&mut self, engine: &mut Engine) {
+        let wood = engine.world.add_material(Material::opaque(vec3(0.6, 0.4, 0.2), 0.8));
+        let dark_wood = engine.world.add_material(Material::opaque(vec3(0.35, 0.22, 0.1), 0.8));
+
+        let mut model = VoxelModel::new(uvec3(4, 4, 4));
+        for z in 0..4 {
+            for y in 0..4 {
+                for x in 0..4 {
+                    let material = if (x + y + z) % 2 == 0 { wood } else { dark_wood };
+                    model.set(ivec3(x, y, z), material);
+                }
+            }
+        }
+
+        let model = engine.entities.add_model(model);
+        let prop = engine.entities.spawn();
+        engine.entities.insert(prop, model);
+        engine.entities.insert(prop, Transform::new(vec3(-8.0, 0.0, -4.0)));
+        self.prop = Some(prop);
+    }
+
+    fn update(&mut self, engine: &mut Engine, delta_time: f32) {
+        let Some(transform) = self.prop.and_then(|prop| engine.entities.get_mut::<Transform>(prop)) else {
+            return;
+        };
+        transform.rotation = Quat::from_rotation_y(delta_time * 0.5) * transform.rotation;
+    }
+}
 
 fn main() {
-    std::env::set_var("RUST_LOG", "error");
-    pretty_env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let settings = match Settings::from_args(args) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    settings.init_logger();
 
-    pollster::block_on(run());
+    pollster::block_on(run(Viewer::default(), settings));
 }
@@ -0,0 +1,442 @@
+use glam::{ivec3, vec3, BVec3, IVec3, Vec3};
+
+use crate::world::VoxelWorld;
+
+/// Keeps boxes from snagging on the faces of voxels they are resting against.
+const EPSILON: f32 = 0.001;
+/// Distance probed below a box to decide whether it stands on something.
+const GROUND_PROBE: f32 = 0.01;
+/// Furthest a sweep moves along one axis, keeps the voxel by voxel walk short.
+const MAX_SWEEP_DISTANCE: f32 = 256.0;
+/// Furthest a raycast walks, outside the grid nothing is solid so an unlimited ray would never stop.
+const MAX_RAYCAST_DISTANCE: f32 = 4096.0;
+
+/// Anything made of unit voxels a box can collide with, e.g. a [`VoxelWorld`] or a synthetic grid.
+pub trait VoxelCollider {
+    /// World space position of the minimum corner of cell (0, 0, 0).
+    fn origin(&self) -> Vec3 {
+        Vec3::ZERO
+    }
+
+    fn is_solid(&self, cell: IVec3) -> bool;
+}
+
+impl VoxelCollider for VoxelWorld {
+    fn origin(&self) -> Vec3 {
+        VoxelWorld::origin(self)
+    }
+
+    fn is_solid(&self, cell: IVec3) -> bool {
+        self.is_material_solid(self.get(cell))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Box of the given size standing on `feet`, the center of its bottom face.
+    pub fn from_feet(feet: Vec3, size: Vec3) -> Self {
+        let half = vec3(size.x / 2.0, 0.0, size.z / 2.0);
+        Self {
+            min: feet - half,
+            max: feet + half + vec3(0.0, size.y, 0.0),
+        }
+    }
+
+    pub fn feet(&self) -> Vec3 {
+        vec3((self.min.x + self.max.x) / 2.0, self.min.y, (self.min.z + self.max.z) / 2.0)
+    }
+
+    pub fn translate(&self, offset: Vec3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+}
+
+/// Result of moving a box through the grid.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sweep {
+    pub aabb: Aabb,
+    /// Axes on which the motion was cut short by a solid voxel.
+    pub blocked: BVec3,
+}
+
+/// Moves `aabb` by `motion` one axis at a time, vertical first, stopping at the first solid voxel on each.
+pub fn sweep(world: &impl VoxelCollider, aabb: Aabb, motion: Vec3) -> Sweep {
+    // The grid walk would never end for non-finite input, so such a box does not move at all.
+    if !motion.is_finite() || !aabb.min.is_finite() || !aabb.max.is_finite() {
+        return Sweep {
+            aabb,
+            blocked: BVec3::TRUE,
+        };
+    }
+    let origin = world.origin();
+    let mut local = aabb.translate(-origin);
+    let mut blocked = [false; 3];
+
+    for axis in [1, 0, 2] {
+        let allowed = move_axis(world, &local, axis, motion[axis]);
+        blocked[axis] = allowed != motion[axis];
+        let mut offset = Vec3::ZERO;
+        offset[axis] = allowed;
+        local = local.translate(offset);
+    }
+
+    Sweep {
+        aabb: local.translate(origin),
+        blocked: BVec3::from(blocked),
+    }
+}
+
+/// Whether the box rests on a solid voxel.
+pub fn is_grounded(world: &impl VoxelCollider, aabb: Aabb) -> bool {
+    sweep(world, aabb, vec3(0.0, -GROUND_PROBE, 0.0)).blocked.y
+}
+
+/// Whether the box overlaps any solid voxel.
+pub fn intersects(world: &impl VoxelCollider, aabb: Aabb) -> bool {
+    let local = aabb.translate(-world.origin());
+    let (min, max) = cell_range(&local);
+    any_solid(world, min, max)
+}
+
+/// First solid voxel along a ray.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit {
+    pub cell: IVec3,
+    /// Normal of the face the ray entered through, zero if it started inside the voxel.
+    pub normal: IVec3,
+    pub distance: f32,
+}
+
+/// Walks the grid voxel by voxel with the same DDA the raytracer uses, up to `max_distance`, which
+/// is capped at `MAX_RAYCAST_DISTANCE` so `f32::INFINITY` works as "no limit".
+pub fn raycast(world: &impl VoxelCollider, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit> {
+    if !origin.is_finite() || max_distance.is_nan() {
+        return None;
+    }
+    let max_distance = max_distance.min(MAX_RAYCAST_DISTANCE);
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+    let local = origin - world.origin();
+    let mut cell = local.floor().as_ivec3();
+    let step = direction.signum().as_ivec3();
+    let delta = direction.recip().abs();
+    let next_boundary = cell.as_vec3() + step.max(IVec3::ZERO).as_vec3();
+    let mut side = (next_boundary - local) / direction;
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            side[axis] = f32::INFINITY;
+        }
+    }
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        if world.is_solid(cell) {
+            return Some(RayHit { cell, normal, distance });
+        }
+        let axis = side.min_element();
+        let axis = if side.x == axis {
+            0
+        } else if side.y == axis {
+            1
+        } else {
+            2
+        };
+        distance = side[axis];
+        side[axis] += delta[axis];
+        cell[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+/// How far the box, in grid space, can move along `axis` before touching a solid voxel. Voxel
+/// layers the box already overlaps are ignored, so a box stuck inside geometry can still get out.
+fn move_axis(world: &impl VoxelCollider, aabb: &Aabb, axis: usize, distance: f32) -> f32 {
+    let distance = distance.clamp(-MAX_SWEEP_DISTANCE, MAX_SWEEP_DISTANCE);
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let (min, max) = cell_range(aabb);
+    let layer_blocked = |layer: i32| {
+        let mut min = min;
+        let mut max = max;
+        min[axis] = layer;
+        max[axis] = layer;
+        any_solid(world, min, max)
+    };
+
+    if distance > 0.0 {
+        let mut layer = (aabb.max[axis] - EPSILON).ceil() as i32;
+        while (layer as f32) < aabb.max[axis] + distance {
+            if layer_blocked(layer) {
+                return (layer as f32 - aabb.max[axis]).clamp(0.0, distance);
+            }
+            layer += 1;
+        }
+    } else {
+        let mut layer = (aabb.min[axis] + EPSILON).floor() as i32 - 1;
+        while (layer + 1) as f32 > aabb.min[axis] + distance {
+            if layer_blocked(layer) {
+                return ((layer + 1) as f32 - aabb.min[axis]).clamp(distance, 0.0);
+            }
+            layer -= 1;
+        }
+    }
+    distance
+}
+
+fn any_solid(world: &impl VoxelCollider, min: IVec3, max: IVec3) -> bool {
+    (min.z..=max.z).any(|z| (min.y..=max.y).any(|y| (min.x..=max.x).any(|x| world.is_solid(ivec3(x, y, z)))))
+}
+
+/// Inclusive range of cells the box overlaps, ignoring faces it only touches.
+fn cell_range(aabb: &Aabb) -> (IVec3, IVec3) {
+    let min = (aabb.min + EPSILON).floor().as_ivec3();
+    let max = (aabb.max - EPSILON).ceil().as_ivec3() - 1;
+    (min, max)
+}
+
+/// Walking body with gravity, jumping and automatic stepping up onto ledges.
+pub struct CharacterController {
+    /// Center of the bottom face of the collision box.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Width, height and depth of the collision box.
+    pub size: Vec3,
+    /// Highest ledge walked onto without jumping.
+    pub step_height: f32,
+    pub gravity: f32,
+    grounded: bool,
+}
+
+impl CharacterController {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            velocity: Vec3::ZERO,
+            size: vec3(0.6, 1.8, 0.6),
+            step_height: 1.0,
+            gravity: 20.0,
+            grounded: false,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_feet(self.position, self.size)
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Overrides the grounded flag until the next update, for restoring a saved state.
+    pub fn set_grounded(&mut self, grounded: bool) {
+        self.grounded = grounded;
+    }
+
+    /// Starts a jump reaching `height` if the body is on the ground.
+    pub fn jump(&mut self, height: f32) {
+        if self.grounded {
+            self.velocity.y = (2.0 * self.gravity * height).sqrt();
+            self.grounded = false;
+        }
+    }
+
+    /// Advances the body by `delta_time`, walking horizontally with `walk_velocity`.
+    pub fn update(&mut self, world: &impl VoxelCollider, walk_velocity: Vec3, delta_time: f32) {
+        self.velocity.x = walk_velocity.x;
+        self.velocity.z = walk_velocity.z;
+        self.velocity.y -= self.gravity * delta_time;
+
+        let aabb = self.aabb();
+        let motion = self.velocity * delta_time;
+        let mut result = sweep(world, aabb, motion);
+
+        let horizontal_blocked = result.blocked.x || result.blocked.z;
+        if self.grounded && horizontal_blocked && self.step_height > 0.0 {
+            if let Some(stepped) = self.step_up(world, aabb, motion) {
+                result = stepped;
+            }
+        }
+
+        if result.blocked.y {
+            self.velocity.y = 0.0;
+        }
+        self.position = result.aabb.feet();
+        self.grounded = is_grounded(world, result.aabb);
+    }
+
+    /// Retries a blocked horizontal move from `step_height` higher, then drops back down onto the
+    /// ledge. Only taken if it gets further than the blocked move did.
+    fn step_up(&self, world: &impl VoxelCollider, aabb: Aabb, motion: Vec3) -> Option<Sweep> {
+        let horizontal = vec3(motion.x, 0.0, motion.z);
+        let blocked = sweep(world, aabb, horizontal);
+
+        let raised = sweep(world, aabb, vec3(0.0, self.step_height, 0.0)).aabb;
+        let moved = sweep(world, raised, horizontal);
+        let lowered = sweep(world, moved.aabb, vec3(0.0, -(moved.aabb.min.y - aabb.min.y), 0.0));
+
+        let progress = |sweep: &Sweep| {
+            let offset = sweep.aabb.min - aabb.min;
+            vec3(offset.x, 0.0, offset.z).length_squared()
+        };
+        if progress(&lowered) <= progress(&blocked) + EPSILON {
+            return None;
+        }
+
+        Some(Sweep {
+            aabb: lowered.aabb,
+            blocked: BVec3::new(moved.blocked.x, true, moved.blocked.z),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const BODY: Vec3 = vec3(0.6, 1.8, 0.6);
+    const DELTA_TIME: f32 = 1.0 / 60.0;
+
+    /// Solid cells listed by hand, with a floor under y = 0.
+    struct Grid(HashSet<IVec3>);
+
+    impl Grid {
+        fn floor() -> Self {
+            let mut cells = HashSet::new();
+            for x in -8..24 {
+                for z in -8..8 {
+                    cells.insert(ivec3(x, -1, z));
+                }
+            }
+            Self(cells)
+        }
+
+        /// Fills the cells from `min` to `max`, inclusive.
+        fn fill(mut self, min: IVec3, max: IVec3) -> Self {
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        self.0.insert(ivec3(x, y, z));
+                    }
+                }
+            }
+            self
+        }
+    }
+
+    impl VoxelCollider for Grid {
+        fn is_solid(&self, cell: IVec3) -> bool {
+            self.0.contains(&cell)
+        }
+    }
+
+    /// Walks a body standing at the origin towards +x for `seconds`.
+    fn walk(world: &Grid, seconds: f32) -> CharacterController {
+        let mut controller = CharacterController::new(Vec3::ZERO);
+        for _ in 0..(seconds / DELTA_TIME) as u32 {
+            controller.update(world, vec3(4.0, 0.0, 0.0), DELTA_TIME);
+        }
+        controller
+    }
+
+    #[test]
+    fn sweep_stops_at_a_wall() {
+        let world = Grid::floor().fill(ivec3(3, 0, -8), ivec3(3, 3, 8));
+        let result = sweep(&world, Aabb::from_feet(Vec3::ZERO, BODY), vec3(5.0, 0.0, 0.0));
+
+        assert_eq!(result.aabb.max.x, 3.0);
+        assert!(result.blocked.x);
+        assert!(!result.blocked.y && !result.blocked.z);
+    }
+
+    #[test]
+    fn boxes_are_grounded_only_on_the_floor() {
+        let world = Grid::floor();
+
+        assert!(is_grounded(&world, Aabb::from_feet(Vec3::ZERO, BODY)));
+        assert!(!is_grounded(&world, Aabb::from_feet(vec3(0.0, 0.5, 0.0), BODY)));
+    }
+
+    #[test]
+    fn controller_steps_onto_a_ledge() {
+        let world = Grid::floor().fill(ivec3(2, 0, -8), ivec3(20, 0, 8));
+        let controller = walk(&world, 2.0);
+
+        assert!(controller.position.x > 4.0);
+        assert!((controller.position.y - 1.0).abs() < EPSILON);
+        assert!(controller.is_grounded());
+    }
+
+    #[test]
+    fn controller_does_not_step_above_the_step_height() {
+        let world = Grid::floor().fill(ivec3(2, 0, -8), ivec3(20, 1, 8));
+        let controller = walk(&world, 2.0);
+
+        assert!((controller.aabb().max.x - 2.0).abs() < EPSILON);
+        assert_eq!(controller.position.y, 0.0);
+    }
+
+    #[test]
+    fn fast_boxes_do_not_tunnel_through_thin_walls() {
+        let world = Grid::floor().fill(ivec3(5, 0, -8), ivec3(5, 3, 8));
+        let result = sweep(&world, Aabb::from_feet(Vec3::ZERO, BODY), vec3(1.0e6, 0.0, 0.0));
+
+        assert_eq!(result.aabb.max.x, 5.0);
+        assert!(result.blocked.x);
+    }
+
+    #[test]
+    fn raycast_hits_the_first_solid_voxel() {
+        let world = Grid::floor().fill(ivec3(5, 0, 0), ivec3(5, 0, 0));
+        let hit = raycast(&world, vec3(0.5, 0.5, 0.5), Vec3::X, 10.0).unwrap();
+
+        assert_eq!(hit.cell, ivec3(5, 0, 0));
+        assert_eq!(hit.normal, ivec3(-1, 0, 0));
+        assert_eq!(hit.distance, 4.5);
+        assert_eq!(raycast(&world, vec3(0.5, 0.5, 0.5), Vec3::X, 4.0), None);
+    }
+
+    #[test]
+    fn unlimited_and_non_finite_raycasts_finish() {
+        let world = Grid::floor();
+
+        assert_eq!(raycast(&world, vec3(0.5, 0.5, 0.5), Vec3::Y, f32::INFINITY), None);
+        assert_eq!(raycast(&world, vec3(0.5, 0.5, 0.5), Vec3::Y, f32::NAN), None);
+        assert_eq!(raycast(&world, vec3(f32::NAN, 0.5, 0.5), Vec3::Y, 10.0), None);
+        assert_eq!(raycast(&world, vec3(0.5, 0.5, 0.5), vec3(f32::INFINITY, 0.0, 0.0), 10.0), None);
+        assert!(raycast(&world, vec3(0.5, 0.5, 0.5), Vec3::NEG_Y, f32::INFINITY).is_some());
+    }
+
+    #[test]
+    fn non_finite_motion_leaves_the_box_in_place() {
+        let world = Grid::floor();
+        let aabb = Aabb::from_feet(Vec3::ZERO, BODY);
+
+        for motion in [
+            vec3(f32::NAN, 0.0, 0.0),
+            vec3(0.0, f32::INFINITY, 0.0),
+            vec3(0.0, 0.0, f32::NEG_INFINITY),
+        ] {
+            assert_eq!(sweep(&world, aabb, motion).aabb, aabb);
+        }
+    }
+}
@@ -34,6 +34,60 @@ impl BindGroupContainer {
             label: Some(label),
         })
     }
+    /// Layout with one entry per binding type, bound at consecutive binding indices.
+    pub fn create_layout_with_entries(
+        types: &[wgpu::BindingType],
+        visibility: wgpu::ShaderStages,
+        context: &GpuContext,
+        label: &str,
+    ) -> wgpu::BindGroupLayout {
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = types
+            .iter()
+            .enumerate()
+            .map(|(binding, ty)| wgpu::BindGroupLayoutEntry {
+                binding: binding as u32,
+                visibility,
+                ty: *ty,
+                count: None,
+            })
+            .collect();
+
+        context.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some(label),
+        })
+    }
+    pub fn buffer_binding(ty: wgpu::BufferBindingType) -> wgpu::BindingType {
+        wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+    pub fn texture_binding() -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+    pub fn unfilterable_texture_binding() -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+    pub fn texture_3d_binding() -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D3,
+            multisampled: false,
+        }
+    }
+    pub fn sampler_binding() -> wgpu::BindingType {
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+    }
     pub fn create_bind_group(
         &mut self,
         binding: u32,
@@ -43,7 +97,7 @@ impl BindGroupContainer {
         layout: &wgpu::BindGroupLayout,
     ) {
         let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &layout,
+            layout,
             entries: &[wgpu::BindGroupEntry {
                 binding,
                 resource: content.as_entire_binding(),
@@ -52,6 +106,29 @@ impl BindGroupContainer {
         });
         self.0.insert(label.to_string(), bind_group);
     }
+    pub fn create_bind_group_with_entries(
+        &mut self,
+        resources: &[wgpu::BindingResource],
+        context: &GpuContext,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+    ) {
+        let entries: Vec<wgpu::BindGroupEntry> = resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.clone(),
+            })
+            .collect();
+
+        let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some(label),
+        });
+        self.0.insert(label.to_string(), bind_group);
+    }
 }
 
 pub struct BufferContainer(HashMap<String, wgpu::Buffer>);
@@ -77,6 +154,23 @@ impl BufferContainer {
         });
         self.0.insert(label.to_string(), buffer);
     }
+    pub fn create_storage_buffer(&mut self, context: &GpuContext, label: &str, size: u64) {
+        let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            label: Some(label),
+        });
+        self.0.insert(label.to_string(), buffer);
+    }
+    pub fn create_storage_buffer_init(&mut self, contents: &[u8], context: &GpuContext, label: &str) {
+        let buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        self.0.insert(label.to_string(), buffer);
+    }
     pub fn create_uniform_buffer(&mut self, context: &GpuContext, label: &str, size: u64) {
         let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
             size,
@@ -94,6 +188,15 @@ impl BufferContainer {
         });
         self.0.insert(label.to_string(), buffer);
     }
+    pub fn create_vertex_buffer(&mut self, context: &GpuContext, label: &str, size: u64) {
+        let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            label: Some(label),
+        });
+        self.0.insert(label.to_string(), buffer);
+    }
     pub fn create_vertex_buffer_init(&mut self, contents: &[u8], context: &GpuContext, label: &str) {
         let buffer = context.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(label),
@@ -103,3 +206,67 @@ impl BufferContainer {
         self.0.insert(label.to_string(), buffer);
     }
 }
+
+pub struct TextureContainer(HashMap<String, (wgpu::Texture, wgpu::TextureView)>);
+
+impl TextureContainer {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    pub fn get_texture(&self, name: &str) -> &wgpu::Texture {
+        if let Some((texture, _)) = self.0.get(name) {
+            return texture;
+        }
+        log::error!("Texture name not recognized: {}.", name);
+        panic!();
+    }
+
+    pub fn get_view(&self, name: &str) -> &wgpu::TextureView {
+        if let Some((_, view)) = self.0.get(name) {
+            return view;
+        }
+        log::error!("Texture name not recognized: {}.", name);
+        panic!();
+    }
+
+    pub fn create_render_texture(&mut self, context: &GpuContext, label: &str, format: wgpu::TextureFormat, width: u32, height: u32) {
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.0.insert(label.to_string(), (texture, view));
+    }
+    pub fn create_depth_texture(&mut self, context: &GpuContext, label: &str, format: wgpu::TextureFormat, width: u32, height: u32) {
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.0.insert(label.to_string(), (texture, view));
+    }
+}
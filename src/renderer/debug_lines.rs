@@ -0,0 +1,119 @@
+use crate::{
+    debug_draw::{DebugDraw, DebugVertex},
+    GpuContext,
+};
+
+use super::{
+    containers::{BindGroupContainer, BufferContainer},
+    pipeline_builder::PiplineBuilder,
+    DEPTH_FORMAT,
+};
+
+const INITIAL_CAPACITY: u64 = 1024;
+
+/// Rasterizes the frame's debug lines into the HDR target, depth tested against the raytraced voxels.
+pub struct DebugLinePass {
+    bind_groups: BindGroupContainer,
+    buffers: BufferContainer,
+    capacity: u64,
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_count: u32,
+}
+
+impl DebugLinePass {
+    pub fn new(context: &GpuContext, camera_buffer: &wgpu::Buffer, frame_buffer: &wgpu::Buffer, format: wgpu::TextureFormat) -> Self {
+        let mut buffers = BufferContainer::new();
+        buffers.create_vertex_buffer(
+            context,
+            "Debug vertex buffer",
+            INITIAL_CAPACITY * std::mem::size_of::<DebugVertex>() as u64,
+        );
+
+        let layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+            ],
+            wgpu::ShaderStages::VERTEX,
+            context,
+            "Debug line bind group",
+        );
+        let mut bind_groups = BindGroupContainer::new();
+        bind_groups.create_bind_group_with_entries(
+            &[camera_buffer.as_entire_binding(), frame_buffer.as_entire_binding()],
+            context,
+            "Debug line bind group",
+            &layout,
+        );
+
+        let mut pipeline_builder = PiplineBuilder::new();
+        pipeline_builder.set_shader_module("shaders/debug_lines.wgsl", "vs_main", "fs_main");
+        pipeline_builder.set_pixel_format(format);
+        pipeline_builder.set_vertex_layout(DebugVertex::description(), wgpu::PrimitiveTopology::LineList);
+        pipeline_builder.set_depth(DEPTH_FORMAT, wgpu::CompareFunction::LessEqual, true);
+        let render_pipeline = pipeline_builder.build(&context.device, &[&layout]);
+
+        Self {
+            bind_groups,
+            buffers,
+            capacity: INITIAL_CAPACITY,
+            render_pipeline,
+            vertex_count: 0,
+        }
+    }
+
+    pub fn upload(&mut self, context: &GpuContext, debug_draw: &DebugDraw) {
+        let vertices = debug_draw.vertices();
+        if vertices.len() as u64 > self.capacity {
+            self.capacity = (vertices.len() as u64).next_power_of_two();
+            self.buffers.create_vertex_buffer(
+                context,
+                "Debug vertex buffer",
+                self.capacity * std::mem::size_of::<DebugVertex>() as u64,
+            );
+        }
+        context
+            .queue
+            .write_buffer(self.buffers.get("Debug vertex buffer"), 0, bytemuck::cast_slice(vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    /// True if the last upload had no lines, `render` should be skipped then.
+    pub fn is_empty(&self) -> bool {
+        self.vertex_count == 0
+    }
+
+    pub fn render(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug line render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes,
+            ..Default::default()
+        });
+        render_pass.set_bind_group(0, self.bind_groups.get("Debug line bind group"), &[]);
+        render_pass.set_vertex_buffer(0, self.buffers.get("Debug vertex buffer").slice(..));
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
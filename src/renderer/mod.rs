@@ -1,18 +1,59 @@
+use egui::Context;
+use glam::{vec2, vec3, Mat4, Vec2, Vec3};
 use winit::window::Window;
 
 use crate::{
-    camera::{Camera, CameraUniform},
+    app::Engine,
+    camera::CameraUniform,
+    debug_draw::DebugDraw,
+    entity::{Entities, InstanceUniform},
     gui::{gui, EguiRenderer},
+    settings::MIN_RENDER_SCALE,
+    world::{VoxelWorld, AIR},
     GpuContext,
 };
 
 use self::{
-    containers::{BindGroupContainer, BufferContainer},
+    containers::{BindGroupContainer, BufferContainer, TextureContainer},
+    debug_lines::DebugLinePass,
     pipeline_builder::PiplineBuilder,
+    post_process::{PostProcessChain, POST_TEXTURES},
+    profiler::GpuProfiler,
+    settings::{AntiAliasing, FogUniform, PostEffect, RenderMode, RenderSettings, Upscaler},
+    taa::TaaPass,
+    tonemap::TonemapPass,
+    upscale::UpscalePass,
 };
 
 pub mod containers;
+mod debug_lines;
 mod pipeline_builder;
+mod post_process;
+pub mod profiler;
+pub mod settings;
+mod taa;
+mod tonemap;
+mod upscale;
+
+const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const DISTANCE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+/// Format of the depth buffer the raytracer writes, raster pipelines drawn on top of the voxels test against it.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const INITIAL_INSTANCE_CAPACITY: u64 = 16;
+/// Mirrors `SUN_DIRECTION` in shader.wgsl.
+const SUN_DIRECTION: Vec3 = vec3(0.4, 0.8, 0.3);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FrameUniform {
+    resolution: [f32; 2],
+    /// Sub-pixel offset of the primary rays in normalized device coordinates.
+    jitter: [f32; 2],
+    frame_index: u32,
+    samples: u32,
+    render_mode: u32,
+    instance_count: u32,
+}
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -50,60 +91,294 @@ const INDICES: &[u16] = &[
 pub struct Renderer {
     bind_groups: BindGroupContainer,
     buffers: BufferContainer,
+    debug_lines: DebugLinePass,
+    frame_index: u32,
+    instance_capacity: u64,
+    /// Revision of the entity models currently in the model voxel buffer.
+    model_revision: u32,
+    post_process: PostProcessChain,
+    previous_view_projection: Mat4,
+    profiler: GpuProfiler,
     render_pipeline: wgpu::RenderPipeline,
+    /// Resolution the raytracer runs at, the render scale applied to the surface size.
+    render_size: (u32, u32),
+    settings: RenderSettings,
+    taa: TaaPass,
+    textures: TextureContainer,
+    tonemap: TonemapPass,
+    upscale: UpscalePass,
+    world_bounds: (Vec3, Vec3),
+    world_layout: wgpu::BindGroupLayout,
 }
 
 impl Renderer {
-    pub fn new(context: &GpuContext) -> Self {
-        let bind_group_layouts: [&wgpu::BindGroupLayout; 2];
-
+    pub fn new(context: &GpuContext, world: &VoxelWorld) -> Self {
         let mut buffers = BufferContainer::new();
         let mut bind_groups = BindGroupContainer::new();
 
         buffers.create_vertex_buffer_init(bytemuck::cast_slice(VERTICES), context, "Vertex buffer");
         buffers.create_index_buffer_init(bytemuck::cast_slice(INDICES), context, "Index buffer");
         buffers.create_uniform_buffer(context, "Camera buffer", std::mem::size_of::<CameraUniform>() as u64);
-        buffers.create_uniform_buffer(context, "Frame data buffer", 16);
+        buffers.create_uniform_buffer(context, "Frame data buffer", std::mem::size_of::<FrameUniform>() as u64);
+        buffers.create_uniform_buffer_init(bytemuck::cast_slice(&[world.get_uniform()]), context, "World buffer");
+        buffers.create_uniform_buffer(context, "Fog buffer", std::mem::size_of::<FogUniform>() as u64);
+        buffers.create_storage_buffer_init(bytemuck::cast_slice(world.voxels()), context, "Voxel buffer");
+        buffers.create_storage_buffer_init(bytemuck::cast_slice(world.materials()), context, "Material buffer");
+        buffers.create_storage_buffer(
+            context,
+            "Instance buffer",
+            INITIAL_INSTANCE_CAPACITY * std::mem::size_of::<InstanceUniform>() as u64,
+        );
+        // Storage buffers can not be empty, so the model voxels start out as a single air voxel.
+        buffers.create_storage_buffer_init(bytemuck::cast_slice(&[AIR]), context, "Model voxel buffer");
 
         let binding_0 = BindGroupContainer::create_layout(0, context, "Frame data bind group");
         let binding_1 = BindGroupContainer::create_layout(0, context, "Camera bind group");
+        let binding_2 = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: true }),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: true }),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: true }),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: true }),
+            ],
+            wgpu::ShaderStages::FRAGMENT,
+            context,
+            "World bind group",
+        );
+        let binding_3 = BindGroupContainer::create_layout(0, context, "Settings bind group");
 
-        bind_group_layouts = [&binding_0, &binding_1];
+        let bind_group_layouts = [&binding_0, &binding_1, &binding_2, &binding_3];
         bind_groups.create_bind_group(
             0,
-            &buffers.get("Frame data buffer"),
+            buffers.get("Frame data buffer"),
             context,
             "Frame data bind group",
             bind_group_layouts[0],
         );
-        bind_groups.create_bind_group(
-            0,
-            &buffers.get("Camera buffer"),
-            context,
-            "Camera bind group",
-            bind_group_layouts[1],
-        );
+        bind_groups.create_bind_group(0, buffers.get("Camera buffer"), context, "Camera bind group", bind_group_layouts[1]);
+        Self::create_world_bind_group(&mut bind_groups, &buffers, context, bind_group_layouts[2]);
+        bind_groups.create_bind_group(0, buffers.get("Fog buffer"), context, "Settings bind group", bind_group_layouts[3]);
 
         let mut pipeline_builder = PiplineBuilder::new();
         pipeline_builder.set_shader_module("shaders/shader.wgsl", "vs_main", "fs_main");
-        pipeline_builder.set_pixel_format(context.surface_config.format);
+        pipeline_builder.set_pixel_formats(&[HDR_FORMAT, DISTANCE_FORMAT]);
+        pipeline_builder.set_depth(DEPTH_FORMAT, wgpu::CompareFunction::Always, true);
         let render_pipeline = pipeline_builder.build(&context.device, &bind_group_layouts);
 
+        let settings = RenderSettings::new();
+        let render_size = settings
+            .resolution
+            .render_size(context.surface_config.width, context.surface_config.height);
+
+        let mut textures = TextureContainer::new();
+        Self::create_render_targets(&mut textures, context, render_size);
+        let debug_lines = DebugLinePass::new(context, buffers.get("Camera buffer"), buffers.get("Frame data buffer"), HDR_FORMAT);
+        let taa = TaaPass::new(context, &textures, buffers.get("Camera buffer"), HDR_FORMAT);
+        let upscale = UpscalePass::new(context, &textures, HDR_FORMAT);
+        let tonemap = TonemapPass::new(context, &textures, context.surface_config.format);
+        let post_process = PostProcessChain::new(context, &textures, context.surface_config.format);
+
         Self {
             bind_groups,
             buffers,
+            debug_lines,
+            frame_index: 0,
+            instance_capacity: INITIAL_INSTANCE_CAPACITY,
+            model_revision: 0,
+            post_process,
+            previous_view_projection: Mat4::IDENTITY,
+            profiler: GpuProfiler::new(context),
             render_pipeline,
+            render_size,
+            settings,
+            taa,
+            textures,
+            tonemap,
+            upscale,
+            world_bounds: (world.origin(), world.origin() + world.size().as_vec3()),
+            world_layout: binding_2,
+        }
+    }
+
+    /// Sets the fraction of the surface resolution the raytracer runs at, from 0.25 to 1. NaN is
+    /// ignored.
+    pub fn set_render_scale(&mut self, context: &GpuContext, scale: f32) {
+        if scale.is_nan() {
+            return;
+        }
+        self.settings.resolution.scale = scale.clamp(MIN_RENDER_SCALE, 1.0);
+        self.update_render_targets(context);
+    }
+
+    pub fn resize(&mut self, context: &GpuContext) {
+        self.render_size = self
+            .settings
+            .resolution
+            .render_size(context.surface_config.width, context.surface_config.height);
+        Self::create_render_targets(&mut self.textures, context, self.render_size);
+        self.bind_scaled_targets(context);
+        self.post_process.set_inputs(context, &self.textures);
+        // The history texture was recreated along with the other full resolution targets.
+        self.taa.reset();
+    }
+
+    /// Recreates only the textures the raytracer renders at the scaled resolution, when the scale
+    /// changed but the window did not. The full resolution targets, and with them the TAA history, stay.
+    fn update_render_targets(&mut self, context: &GpuContext) {
+        let render_size = self
+            .settings
+            .resolution
+            .render_size(context.surface_config.width, context.surface_config.height);
+        if render_size == self.render_size {
+            return;
+        }
+        self.render_size = render_size;
+        let had_history = self.taa.has_history();
+        Self::create_scaled_targets(&mut self.textures, context, render_size);
+        self.bind_scaled_targets(context);
+        debug_assert_eq!(
+            self.taa.has_history(),
+            had_history,
+            "A render scale change must keep the TAA history."
+        );
+    }
+
+    fn bind_scaled_targets(&mut self, context: &GpuContext) {
+        self.taa.set_inputs(context, &self.textures, self.buffers.get("Camera buffer"));
+        self.upscale.set_inputs(context, &self.textures);
+        self.tonemap.set_inputs(context, &self.textures);
+    }
+
+    fn create_render_targets(textures: &mut TextureContainer, context: &GpuContext, render_size: (u32, u32)) {
+        Self::create_scaled_targets(textures, context, render_size);
+
+        let (width, height) = (context.surface_config.width, context.surface_config.height);
+        textures.create_render_texture(context, "Upscaled texture", HDR_FORMAT, width, height);
+        textures.create_render_texture(context, "Resolved texture", HDR_FORMAT, width, height);
+        textures.create_render_texture(context, "History texture", HDR_FORMAT, width, height);
+        for label in POST_TEXTURES {
+            textures.create_render_texture(context, label, context.surface_config.format, width, height);
+        }
+    }
+
+    fn create_scaled_targets(textures: &mut TextureContainer, context: &GpuContext, render_size: (u32, u32)) {
+        let (render_width, render_height) = render_size;
+        textures.create_render_texture(context, "HDR texture", HDR_FORMAT, render_width, render_height);
+        textures.create_render_texture(context, "Distance texture", DISTANCE_FORMAT, render_width, render_height);
+        textures.create_depth_texture(context, "Depth texture", DEPTH_FORMAT, render_width, render_height);
+    }
+
+    fn create_world_bind_group(
+        bind_groups: &mut BindGroupContainer,
+        buffers: &BufferContainer,
+        context: &GpuContext,
+        layout: &wgpu::BindGroupLayout,
+    ) {
+        bind_groups.create_bind_group_with_entries(
+            &[
+                buffers.get("World buffer").as_entire_binding(),
+                buffers.get("Voxel buffer").as_entire_binding(),
+                buffers.get("Material buffer").as_entire_binding(),
+                buffers.get("Instance buffer").as_entire_binding(),
+                buffers.get("Model voxel buffer").as_entire_binding(),
+            ],
+            context,
+            "World bind group",
+            layout,
+        );
+    }
+
+    /// Writes this frame's entity instances, and the models whenever they changed, returning the instance count.
+    fn upload_entities(&mut self, context: &GpuContext, entities: &Entities) -> u32 {
+        let mut rebind = false;
+        if entities.model_revision() != self.model_revision {
+            let mut voxels = entities.model_voxels();
+            if voxels.is_empty() {
+                voxels.push(AIR);
+            }
+            self.buffers
+                .create_storage_buffer_init(bytemuck::cast_slice(&voxels), context, "Model voxel buffer");
+            self.model_revision = entities.model_revision();
+            rebind = true;
+        }
+
+        let instances = entities.get_instances();
+        if instances.len() as u64 > self.instance_capacity {
+            self.instance_capacity = (instances.len() as u64).next_power_of_two();
+            self.buffers.create_storage_buffer(
+                context,
+                "Instance buffer",
+                self.instance_capacity * std::mem::size_of::<InstanceUniform>() as u64,
+            );
+            rebind = true;
+        }
+
+        if rebind {
+            Self::create_world_bind_group(&mut self.bind_groups, &self.buffers, context, &self.world_layout);
+        }
+        context
+            .queue
+            .write_buffer(self.buffers.get("Instance buffer"), 0, bytemuck::cast_slice(&instances));
+        instances.len() as u32
+    }
+
+    fn draw_debug_settings(&mut self, debug_draw: &mut DebugDraw, view_projection: Mat4) {
+        let debug = &mut self.settings.debug;
+        if debug.world_bounds {
+            debug_draw.aabb(self.world_bounds.0, self.world_bounds.1, vec3(1.0, 1.0, 0.0));
+        }
+        if debug.axes {
+            debug_draw.axes(Vec3::ZERO, 1.0);
+        }
+        if debug.sun_direction {
+            debug_draw.line(Vec3::ZERO, SUN_DIRECTION.normalize() * 4.0, vec3(1.0, 0.8, 0.3));
+        }
+        if let Some(view_projection_inverse) = debug.frozen_frustum(view_projection) {
+            debug_draw.frustum(view_projection_inverse, vec3(0.0, 1.0, 1.0));
         }
     }
+
+    fn begin_fullscreen_pass<'a>(
+        command_encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        label: &'a str,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'a>>,
+    ) -> wgpu::RenderPass<'a> {
+        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            timestamp_writes,
+            ..Default::default()
+        })
+    }
+
+    fn draw_fullscreen<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.buffers.get("Vertex buffer").slice(..));
+        render_pass.set_index_buffer(self.buffers.get("Index buffer").slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
+    }
+
     pub fn render(
-        &self,
-        camera: &Camera,
+        &mut self,
+        engine: &mut Engine,
         context: &GpuContext,
         egui: &mut EguiRenderer,
         window: &Window,
-        frametime: u128,
+        app_ui: impl FnOnce(&Context),
     ) -> Result<(), wgpu::SurfaceError> {
-        let drawable = context.surface.get_current_texture()?;
+        self.update_render_size(engine, context);
+
+        let surface = context.surface.as_ref().expect("Rendering to a window needs a surface.");
+        let drawable = surface.get_current_texture()?;
+        self.profiler.begin_frame(context);
         let image_view_descriptor = wgpu::TextureViewDescriptor::default();
         let image_view = drawable.texture.create_view(&image_view_descriptor);
 
@@ -111,36 +386,149 @@ impl Renderer {
             label: Some("Command Encoder"),
         };
         let mut command_encoder = context.device.create_command_encoder(command_encoder_descriptor);
+        self.encode_frame(engine, context, &mut command_encoder, &image_view);
 
-        let color_attachment = wgpu::RenderPassColorAttachment {
-            view: &image_view,
-            resolve_target: None,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                store: wgpu::StoreOp::Store,
+        egui.draw(
+            context,
+            &drawable,
+            &mut command_encoder,
+            |ui| {
+                gui(ui, engine, &mut self.settings, &self.profiler);
+                app_ui(ui);
             },
+            window,
+        );
+
+        self.profiler.resolve(&mut command_encoder);
+        context.queue.submit(std::iter::once(command_encoder.finish()));
+        self.profiler.end_frame();
+
+        drawable.present();
+
+        Ok(())
+    }
+
+    /// Renders a frame into `output`, a texture in the surface format, without the settings
+    /// window. Used by headless contexts.
+    pub fn render_offscreen(&mut self, engine: &mut Engine, context: &GpuContext, output: &wgpu::TextureView) {
+        self.update_render_size(engine, context);
+        self.profiler.begin_frame(context);
+        let command_encoder_descriptor = &wgpu::CommandEncoderDescriptor {
+            label: Some("Command Encoder"),
+        };
+        let mut command_encoder = context.device.create_command_encoder(command_encoder_descriptor);
+        self.encode_frame(engine, context, &mut command_encoder, output);
+
+        self.profiler.resolve(&mut command_encoder);
+        context.queue.submit(std::iter::once(command_encoder.finish()));
+        self.profiler.end_frame();
+    }
+
+    pub fn profiler(&self) -> &GpuProfiler {
+        &self.profiler
+    }
+
+    fn update_render_size(&mut self, engine: &Engine, context: &GpuContext) {
+        // The CPU frame time includes waiting for vsync, which says nothing about the GPU load, so it
+        // is only a fallback when there are no timestamp queries.
+        let frame_time = match self.profiler.frame_history().back() {
+            Some(gpu_frame_time) => *gpu_frame_time,
+            None => engine.frame_timer().delta_time() as f32 * 1000.0,
         };
+        self.settings.resolution.update_dynamic(frame_time);
+        self.update_render_targets(context);
+    }
+
+    /// Records every pass of the frame, from the raytracer to the last post effect writing `image_view`.
+    fn encode_frame(
+        &mut self,
+        engine: &mut Engine,
+        context: &GpuContext,
+        command_encoder: &mut wgpu::CommandEncoder,
+        image_view: &wgpu::TextureView,
+    ) {
+        let instance_count = self.upload_entities(context, &engine.entities);
+
+        let color_attachments = [
+            Some(wgpu::RenderPassColorAttachment {
+                view: self.textures.get_view("HDR texture"),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+            Some(wgpu::RenderPassColorAttachment {
+                view: self.textures.get_view("Distance texture"),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+        ];
 
         let render_pass_descriptor = wgpu::RenderPassDescriptor {
             label: Some("Main render Pass"),
-            color_attachments: &[Some(color_attachment)],
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.textures.get_view("Depth texture"),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: self.profiler.render_pass_timestamps("Raytrace"),
             ..Default::default()
         };
 
-        let mut render_pass = command_encoder.begin_render_pass(&render_pass_descriptor);
-
-        let frame_data: [f32; 2] = [1920.0, 1080.0];
+        let (width, height) = (context.surface_config.width, context.surface_config.height);
+        let (render_width, render_height) = self.render_size;
+        let anti_aliasing = &self.settings.anti_aliasing;
+        let temporal = anti_aliasing.mode == AntiAliasing::Temporal || self.settings.resolution.upscaler == Upscaler::Temporal;
+        let resolution = vec2(render_width as f32, render_height as f32);
+        let jitter = if temporal {
+            taa::jitter(self.frame_index) * 2.0 / resolution
+        } else {
+            Vec2::ZERO
+        };
+        let frame_data = FrameUniform {
+            resolution: resolution.into(),
+            jitter: jitter.into(),
+            frame_index: self.frame_index,
+            samples: match anti_aliasing.mode {
+                AntiAliasing::Supersampling => anti_aliasing.samples,
+                _ => 1,
+            },
+            render_mode: self.settings.debug.render_mode as u32,
+            instance_count,
+        };
+        self.frame_index = self.frame_index.wrapping_add(1);
 
         context
             .queue
-            .write_buffer(&self.buffers.get("Frame data buffer"), 0, bytemuck::cast_slice(&frame_data));
+            .write_buffer(self.buffers.get("Frame data buffer"), 0, bytemuck::cast_slice(&[frame_data]));
 
+        let mut camera_uniform = engine.camera.get_uniform();
+        camera_uniform.set_previous_view_projection(self.previous_view_projection);
+        self.previous_view_projection = camera_uniform.view_projection();
         context
             .queue
-            .write_buffer(&self.buffers.get("Camera buffer"), 0, bytemuck::cast_slice(&[camera.get_uniform()]));
+            .write_buffer(self.buffers.get("Camera buffer"), 0, bytemuck::cast_slice(&[camera_uniform]));
+
+        context.queue.write_buffer(
+            self.buffers.get("Fog buffer"),
+            0,
+            bytemuck::cast_slice(&[self.settings.fog.get_uniform()]),
+        );
 
-        render_pass.set_bind_group(0, &self.bind_groups.get("Frame data bind group"), &[]);
-        render_pass.set_bind_group(1, &self.bind_groups.get("Camera bind group"), &[]);
+        let mut render_pass = command_encoder.begin_render_pass(&render_pass_descriptor);
+
+        render_pass.set_bind_group(0, self.bind_groups.get("Frame data bind group"), &[]);
+        render_pass.set_bind_group(1, self.bind_groups.get("Camera bind group"), &[]);
+        render_pass.set_bind_group(2, self.bind_groups.get("World bind group"), &[]);
+        render_pass.set_bind_group(3, self.bind_groups.get("Settings bind group"), &[]);
         render_pass.set_vertex_buffer(0, self.buffers.get("Vertex buffer").slice(..));
         render_pass.set_index_buffer(self.buffers.get("Index buffer").slice(..), wgpu::IndexFormat::Uint16);
 
@@ -148,12 +536,112 @@ impl Renderer {
         render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
         drop(render_pass);
 
-        egui.draw(context, &drawable, &mut command_encoder, |ui| gui(ui, frametime), window);
+        let debug_draw = &mut engine.debug_draw;
+        self.draw_debug_settings(debug_draw, camera_uniform.view_projection());
+        self.debug_lines.upload(context, debug_draw);
+        debug_draw.clear();
+        // Only allocate timestamps for passes that are recorded, unwritten queries must not be resolved.
+        if !self.debug_lines.is_empty() {
+            self.debug_lines.render(
+                command_encoder,
+                self.textures.get_view("HDR texture"),
+                self.textures.get_view("Depth texture"),
+                self.profiler.render_pass_timestamps("Debug lines"),
+            );
+        }
+
+        let hdr_input = if temporal {
+            self.taa.update(context, &self.settings.anti_aliasing);
+            let mut render_pass = Self::begin_fullscreen_pass(
+                command_encoder,
+                self.textures.get_view("Resolved texture"),
+                "Temporal render pass",
+                self.profiler.render_pass_timestamps("Temporal"),
+            );
+            self.taa.bind(&mut render_pass);
+            self.draw_fullscreen(&mut render_pass);
+            drop(render_pass);
+            self.taa.store_history(command_encoder, &self.textures);
+            "Resolved texture"
+        } else if self.render_size != (width, height) {
+            self.taa.reset();
+            let mut render_pass = Self::begin_fullscreen_pass(
+                command_encoder,
+                self.textures.get_view("Upscaled texture"),
+                "Upscale render pass",
+                self.profiler.render_pass_timestamps("Upscale"),
+            );
+            self.upscale.bind(&mut render_pass, self.settings.resolution.upscaler);
+            self.draw_fullscreen(&mut render_pass);
+            drop(render_pass);
+            "Upscaled texture"
+        } else {
+            self.taa.reset();
+            "HDR texture"
+        };
 
-        context.queue.submit(std::iter::once(command_encoder.finish()));
+        let (input_width, input_height) = if hdr_input == "HDR texture" {
+            self.render_size
+        } else {
+            (width, height)
+        };
+        let tonemap_settings = &self.settings.tonemap;
+        self.tonemap.update(
+            context,
+            tonemap_settings,
+            engine.frame_timer().delta_time() as f32,
+            input_width,
+            input_height,
+            self.settings.debug.render_mode != RenderMode::Lit,
+        );
+        if tonemap_settings.auto_exposure {
+            self.tonemap.compute_exposure(
+                command_encoder,
+                hdr_input,
+                input_width,
+                input_height,
+                self.profiler.compute_pass_timestamps("Exposure"),
+            );
+        }
 
-        drawable.present();
+        let effects: Vec<PostEffect> = self
+            .settings
+            .post_process
+            .iter()
+            .filter(|node| node.enabled)
+            .map(|node| node.effect)
+            .collect();
+        self.post_process.update(context, &self.settings.post_process);
 
-        Ok(())
+        let tonemap_target = if effects.is_empty() {
+            image_view
+        } else {
+            self.textures.get_view(POST_TEXTURES[0])
+        };
+        let mut render_pass = Self::begin_fullscreen_pass(
+            command_encoder,
+            tonemap_target,
+            "Tonemap render pass",
+            self.profiler.render_pass_timestamps("Tonemap"),
+        );
+        self.tonemap.bind(&mut render_pass, hdr_input);
+        self.draw_fullscreen(&mut render_pass);
+        drop(render_pass);
+
+        for (index, effect) in effects.iter().enumerate() {
+            let output = if index + 1 == effects.len() {
+                image_view
+            } else {
+                self.textures.get_view(POST_TEXTURES[(index + 1) % 2])
+            };
+            let mut render_pass = Self::begin_fullscreen_pass(
+                command_encoder,
+                output,
+                effect.name(),
+                self.profiler.render_pass_timestamps(effect.name()),
+            );
+            self.post_process.bind(&mut render_pass, *effect, POST_TEXTURES[index % 2]);
+            self.draw_fullscreen(&mut render_pass);
+        }
     }
 }
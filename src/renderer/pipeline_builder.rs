@@ -3,19 +3,27 @@ use std::{borrow::Cow, env::current_dir, fs};
 use super::Vertex;
 
 pub struct PiplineBuilder {
+    compute_entry: String,
+    depth_stencil: Option<wgpu::DepthStencilState>,
     fragment_entry: String,
-    pixel_format: wgpu::TextureFormat,
+    pixel_formats: Vec<wgpu::TextureFormat>,
     shader_filename: String,
+    topology: wgpu::PrimitiveTopology,
     vertex_entry: String,
+    vertex_layout: wgpu::VertexBufferLayout<'static>,
 }
 
 impl PiplineBuilder {
     pub fn new() -> Self {
         Self {
+            compute_entry: "".to_string(),
+            depth_stencil: None,
             fragment_entry: "".to_string(),
-            pixel_format: wgpu::TextureFormat::Rgba8Unorm,
+            pixel_formats: vec![wgpu::TextureFormat::Rgba8Unorm],
             shader_filename: "".to_string(),
+            topology: wgpu::PrimitiveTopology::TriangleList,
             vertex_entry: "".to_string(),
+            vertex_layout: Vertex::description(),
         }
     }
 
@@ -25,22 +33,38 @@ impl PiplineBuilder {
         self.fragment_entry = fragment_entry.to_string();
     }
 
+    pub fn set_compute_shader_module(&mut self, shader_filename: &str, compute_entry: &str) {
+        self.shader_filename = shader_filename.to_string();
+        self.compute_entry = compute_entry.to_string();
+    }
+
     pub fn set_pixel_format(&mut self, pixel_format: wgpu::TextureFormat) {
-        self.pixel_format = pixel_format;
+        self.pixel_formats = vec![pixel_format];
     }
 
-    pub fn build(&self, device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout; 2]) -> wgpu::RenderPipeline {
-        let mut filepath = current_dir().unwrap();
-        filepath.push("src/");
-        filepath.push(self.shader_filename.as_str());
-        let filepath = filepath.into_os_string().into_string().unwrap();
-        let source_code: Cow<'_, str> = fs::read_to_string(filepath).unwrap().into();
+    /// One color target per format, in `@location` order.
+    pub fn set_pixel_formats(&mut self, pixel_formats: &[wgpu::TextureFormat]) {
+        self.pixel_formats = pixel_formats.to_vec();
+    }
 
-        let shader_module_descriptor = wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(source_code),
-        };
-        let shader_module = device.create_shader_module(shader_module_descriptor);
+    pub fn set_vertex_layout(&mut self, vertex_layout: wgpu::VertexBufferLayout<'static>, topology: wgpu::PrimitiveTopology) {
+        self.vertex_layout = vertex_layout;
+        self.topology = topology;
+    }
+
+    /// Attaches a depth buffer, pipelines without one ignore depth entirely.
+    pub fn set_depth(&mut self, format: wgpu::TextureFormat, depth_compare: wgpu::CompareFunction, depth_write_enabled: bool) {
+        self.depth_stencil = Some(wgpu::DepthStencilState {
+            format,
+            depth_write_enabled,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
+    }
+
+    pub fn build(&self, device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout]) -> wgpu::RenderPipeline {
+        let shader_module = self.create_shader_module(device);
 
         let pipeline_layout_descriptor = wgpu::PipelineLayoutDescriptor {
             label: Some("Render pipeline layout"),
@@ -49,11 +73,21 @@ impl PiplineBuilder {
         };
         let render_pipeline_layout = device.create_pipeline_layout(&pipeline_layout_descriptor);
 
-        let render_targets = [Some(wgpu::ColorTargetState {
-            format: self.pixel_format,
-            blend: Some(wgpu::BlendState::REPLACE),
-            write_mask: wgpu::ColorWrites::ALL,
-        })];
+        let render_targets: Vec<Option<wgpu::ColorTargetState>> = self
+            .pixel_formats
+            .iter()
+            .map(|format| {
+                let blendable = format
+                    .guaranteed_format_features(device.features())
+                    .flags
+                    .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE);
+                Some(wgpu::ColorTargetState {
+                    format: *format,
+                    blend: blendable.then_some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+            .collect();
 
         let render_pipeline_descriptor = wgpu::RenderPipelineDescriptor {
             label: Some("Render pipeline"),
@@ -62,11 +96,11 @@ impl PiplineBuilder {
             vertex: wgpu::VertexState {
                 module: &shader_module,
                 entry_point: &self.vertex_entry,
-                buffers: &[Vertex::description()],
+                buffers: std::slice::from_ref(&self.vertex_layout),
             },
 
             primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
+                topology: self.topology,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
                 cull_mode: Some(wgpu::Face::Back),
@@ -81,7 +115,7 @@ impl PiplineBuilder {
                 targets: &render_targets,
             }),
 
-            depth_stencil: None,
+            depth_stencil: self.depth_stencil.clone(),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -92,4 +126,37 @@ impl PiplineBuilder {
 
         device.create_render_pipeline(&render_pipeline_descriptor)
     }
+    pub fn build_compute(&self, device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout]) -> wgpu::ComputePipeline {
+        let shader_module = self.create_shader_module(device);
+
+        let pipeline_layout_descriptor = wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute pipeline layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        };
+        let compute_pipeline_layout = device.create_pipeline_layout(&pipeline_layout_descriptor);
+
+        let compute_pipeline_descriptor = wgpu::ComputePipelineDescriptor {
+            label: Some("Compute pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &shader_module,
+            entry_point: &self.compute_entry,
+        };
+
+        device.create_compute_pipeline(&compute_pipeline_descriptor)
+    }
+
+    fn create_shader_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        let mut filepath = current_dir().unwrap();
+        filepath.push("src/");
+        filepath.push(self.shader_filename.as_str());
+        let filepath = filepath.into_os_string().into_string().unwrap();
+        let source_code: Cow<'_, str> = fs::read_to_string(filepath).unwrap().into();
+
+        let shader_module_descriptor = wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(source_code),
+        };
+        device.create_shader_module(shader_module_descriptor)
+    }
 }
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use glam::{uvec3, vec3, Vec3};
+
+use crate::GpuContext;
+
+use super::{
+    containers::{BindGroupContainer, BufferContainer, TextureContainer},
+    pipeline_builder::PiplineBuilder,
+    settings::{PostEffect, PostProcessNode},
+};
+
+pub const POST_TEXTURES: [&str; 2] = ["Post texture A", "Post texture B"];
+const LUT_SIZE: u32 = 32;
+
+/// Post effects applied after tonemapping, each effect is a node with its own pipeline.
+/// Nodes ping-pong between the two post textures, the last enabled node writes to the surface.
+pub struct PostProcessChain {
+    bind_groups: BindGroupContainer,
+    buffers: BufferContainer,
+    layout: wgpu::BindGroupLayout,
+    lut_texture: wgpu::Texture,
+    lut_values: Option<[f32; 4]>,
+    lut_view: wgpu::TextureView,
+    pipelines: HashMap<PostEffect, wgpu::RenderPipeline>,
+    sampler: wgpu::Sampler,
+}
+
+impl PostProcessChain {
+    pub fn new(context: &GpuContext, textures: &TextureContainer, output_format: wgpu::TextureFormat) -> Self {
+        let mut buffers = BufferContainer::new();
+        for effect in PostEffect::ALL {
+            buffers.create_uniform_buffer(context, effect.name(), 16);
+        }
+
+        let layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::sampler_binding(),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::texture_3d_binding(),
+            ],
+            wgpu::ShaderStages::FRAGMENT,
+            context,
+            "Post process bind group",
+        );
+
+        let mut pipelines = HashMap::new();
+        let mut pipeline_builder = PiplineBuilder::new();
+        pipeline_builder.set_pixel_format(output_format);
+        for effect in PostEffect::ALL {
+            pipeline_builder.set_shader_module("shaders/post_process.wgsl", "vs_main", effect.fragment_entry());
+            pipelines.insert(effect, pipeline_builder.build(&context.device, &[&layout]));
+        }
+
+        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post process sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let lut_texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color grading LUT"),
+            size: wgpu::Extent3d {
+                width: LUT_SIZE,
+                height: LUT_SIZE,
+                depth_or_array_layers: LUT_SIZE,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let lut_view = lut_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut chain = Self {
+            bind_groups: BindGroupContainer::new(),
+            buffers,
+            layout,
+            lut_texture,
+            lut_values: None,
+            lut_view,
+            pipelines,
+            sampler,
+        };
+        chain.set_inputs(context, textures);
+        chain
+    }
+
+    /// Rebinds the post textures, needed whenever the render targets are recreated.
+    pub fn set_inputs(&mut self, context: &GpuContext, textures: &TextureContainer) {
+        for effect in PostEffect::ALL {
+            for input in POST_TEXTURES {
+                self.bind_groups.create_bind_group_with_entries(
+                    &[
+                        wgpu::BindingResource::TextureView(textures.get_view(input)),
+                        wgpu::BindingResource::Sampler(&self.sampler),
+                        self.buffers.get(effect.name()).as_entire_binding(),
+                        wgpu::BindingResource::TextureView(&self.lut_view),
+                    ],
+                    context,
+                    &format!("{} {}", effect.name(), input),
+                    &self.layout,
+                );
+            }
+        }
+    }
+
+    pub fn update(&mut self, context: &GpuContext, nodes: &[PostProcessNode]) {
+        for node in nodes.iter().filter(|node| node.enabled) {
+            context
+                .queue
+                .write_buffer(self.buffers.get(node.effect.name()), 0, bytemuck::cast_slice(&node.values));
+
+            if node.effect == PostEffect::ColorGrading && self.lut_values != Some(node.values) {
+                self.write_lut(context, node.values);
+                self.lut_values = Some(node.values);
+            }
+        }
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, effect: PostEffect, input: &str) {
+        render_pass.set_bind_group(0, self.bind_groups.get(&format!("{} {}", effect.name(), input)), &[]);
+        render_pass.set_pipeline(&self.pipelines[&effect]);
+    }
+
+    fn write_lut(&self, context: &GpuContext, values: [f32; 4]) {
+        let [contrast, saturation, temperature, tint] = values;
+
+        let mut texels = Vec::with_capacity((LUT_SIZE * LUT_SIZE * LUT_SIZE * 4) as usize);
+        for b in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for r in 0..LUT_SIZE {
+                    let mut color = uvec3(r, g, b).as_vec3() / (LUT_SIZE - 1) as f32;
+                    color += vec3(temperature, tint, -temperature) * 0.1;
+                    let luminance = color.dot(vec3(0.2126, 0.7152, 0.0722));
+                    color = Vec3::splat(luminance) + (color - luminance) * saturation;
+                    color = (color - 0.5) * contrast + 0.5;
+
+                    let color = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).round();
+                    texels.extend_from_slice(&[color.x as u8, color.y as u8, color.z as u8, 255]);
+                }
+            }
+        }
+
+        context.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.lut_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(LUT_SIZE * 4),
+                rows_per_image: Some(LUT_SIZE),
+            },
+            wgpu::Extent3d {
+                width: LUT_SIZE,
+                height: LUT_SIZE,
+                depth_or_array_layers: LUT_SIZE,
+            },
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use crate::GpuContext;
+
+const MAX_PASSES: u32 = 32;
+/// Number of frames kept for the rolling graph and averages.
+pub const HISTORY_LENGTH: usize = 120;
+
+/// States of a timestamp readback, written by the `map_async` callback.
+const READBACK_PENDING: u8 = 0;
+const READBACK_READY: u8 = 1;
+const READBACK_FAILED: u8 = 2;
+
+/// Per pass GPU durations in milliseconds, averaged over the history.
+pub struct PassTiming {
+    pub label: &'static str,
+    pub average: f32,
+}
+
+/// Measures every render and compute pass with timestamp queries when the adapter supports them.
+///
+/// Results are read back asynchronously, frames recorded while the previous readback is still in
+/// flight are not measured. A failed readback only loses that frame's timings.
+pub struct GpuProfiler {
+    frame_history: VecDeque<f32>,
+    labels: RefCell<Vec<&'static str>>,
+    next_query: Cell<u32>,
+    pass_history: Vec<(&'static str, VecDeque<f32>)>,
+    pending: Option<(Vec<&'static str>, Arc<AtomicU8>)>,
+    queries: Option<Queries>,
+    recording: bool,
+}
+
+struct Queries {
+    query_set: wgpu::QuerySet,
+    readback_buffer: wgpu::Buffer,
+    resolve_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+}
+
+impl GpuProfiler {
+    pub fn new(context: &GpuContext) -> Self {
+        let queries = context.device.features().contains(wgpu::Features::TIMESTAMP_QUERY).then(|| {
+            let size = (MAX_PASSES * 2) as u64 * std::mem::size_of::<u64>() as u64;
+            Queries {
+                query_set: context.device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("Timestamp query set"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: MAX_PASSES * 2,
+                }),
+                readback_buffer: context.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp readback buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                resolve_buffer: context.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp resolve buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                period: context.queue.get_timestamp_period(),
+            }
+        });
+        if queries.is_none() {
+            log::info!("Timestamp queries are not supported, GPU profiling is disabled.");
+        }
+
+        Self {
+            frame_history: VecDeque::with_capacity(HISTORY_LENGTH),
+            labels: RefCell::new(Vec::new()),
+            next_query: Cell::new(0),
+            pass_history: Vec::new(),
+            pending: None,
+            queries,
+            recording: false,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.queries.is_some()
+    }
+
+    /// Collects the previous readback if it finished and decides whether this frame gets measured.
+    pub fn begin_frame(&mut self, context: &GpuContext) {
+        self.labels.borrow_mut().clear();
+        self.next_query.set(0);
+        self.recording = false;
+
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        if let Some((labels, state)) = &self.pending {
+            context.device.poll(wgpu::Maintain::Poll);
+            match state.load(Ordering::Acquire) {
+                READBACK_PENDING => return,
+                READBACK_FAILED => {
+                    // A failed map leaves the buffer unmapped, so drop the frame and measure the next one.
+                    log::error!("Failed to read back the GPU timestamps.");
+                    self.pending = None;
+                    self.recording = true;
+                    return;
+                }
+                _ => {}
+            }
+
+            let slice = queries.readback_buffer.slice(..);
+            let timestamps: Vec<u64> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+            queries.readback_buffer.unmap();
+
+            let to_milliseconds = |begin: u64, end: u64| end.saturating_sub(begin) as f32 * queries.period / 1_000_000.0;
+            let mut frame_time = 0.0;
+            for (index, label) in labels.iter().enumerate() {
+                let duration = to_milliseconds(timestamps[index * 2], timestamps[index * 2 + 1]);
+                frame_time += duration;
+                match self.pass_history.iter_mut().find(|(name, _)| name == label) {
+                    Some((_, history)) => push_sample(history, duration),
+                    None => self.pass_history.push((label, VecDeque::from([duration]))),
+                }
+            }
+            // Passes that were not recorded this frame, like a disabled effect, fade out of the breakdown.
+            for (label, history) in &mut self.pass_history {
+                if !labels.contains(label) {
+                    history.pop_front();
+                }
+            }
+            self.pass_history.retain(|(_, history)| !history.is_empty());
+            push_sample(&mut self.frame_history, frame_time);
+            self.pending = None;
+        }
+        self.recording = true;
+    }
+
+    pub fn render_pass_timestamps(&self, label: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let (query_set, index) = self.allocate(label)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub fn compute_pass_timestamps(&self, label: &'static str) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        let (query_set, index) = self.allocate(label)?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Copies this frame's timestamps to the readback buffer, call before submitting the encoder.
+    pub fn resolve(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        let (Some(queries), true) = (&self.queries, self.recording) else {
+            return;
+        };
+        let count = self.next_query.get();
+        if count == 0 {
+            return;
+        }
+        command_encoder.resolve_query_set(&queries.query_set, 0..count, &queries.resolve_buffer, 0);
+        command_encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.readback_buffer,
+            0,
+            count as u64 * std::mem::size_of::<u64>() as u64,
+        );
+    }
+
+    /// Starts reading back the resolved timestamps, call after submitting the encoder.
+    pub fn end_frame(&mut self) {
+        let (Some(queries), true) = (&self.queries, self.recording) else {
+            return;
+        };
+        if self.next_query.get() == 0 {
+            return;
+        }
+
+        let state = Arc::new(AtomicU8::new(READBACK_PENDING));
+        let callback_state = state.clone();
+        queries.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let outcome = if result.is_ok() { READBACK_READY } else { READBACK_FAILED };
+            callback_state.store(outcome, Ordering::Release);
+        });
+        self.pending = Some((self.labels.borrow().clone(), state));
+    }
+
+    pub fn frame_history(&self) -> &VecDeque<f32> {
+        &self.frame_history
+    }
+
+    pub fn pass_timings(&self) -> Vec<PassTiming> {
+        self.pass_history
+            .iter()
+            .map(|(label, history)| PassTiming {
+                label,
+                average: history.iter().sum::<f32>() / history.len() as f32,
+            })
+            .collect()
+    }
+
+    fn allocate(&self, label: &'static str) -> Option<(&wgpu::QuerySet, u32)> {
+        let queries = self.queries.as_ref().filter(|_| self.recording)?;
+        let index = self.next_query.get();
+        if index >= MAX_PASSES * 2 {
+            return None;
+        }
+        self.next_query.set(index + 2);
+        self.labels.borrow_mut().push(label);
+        Some((&queries.query_set, index))
+    }
+}
+
+fn push_sample(history: &mut VecDeque<f32>, sample: f32) {
+    if history.len() == HISTORY_LENGTH {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
@@ -0,0 +1,472 @@
+use glam::Mat4;
+
+pub struct RenderSettings {
+    pub anti_aliasing: AntiAliasingSettings,
+    pub debug: DebugSettings,
+    pub fog: FogSettings,
+    pub post_process: Vec<PostProcessNode>,
+    pub resolution: ResolutionSettings,
+    pub tonemap: TonemapSettings,
+}
+
+impl RenderSettings {
+    pub fn new() -> Self {
+        Self {
+            anti_aliasing: AntiAliasingSettings::new(),
+            debug: DebugSettings::new(),
+            fog: FogSettings::new(),
+            post_process: PostEffect::ALL.into_iter().map(PostProcessNode::new).collect(),
+            resolution: ResolutionSettings::new(),
+            tonemap: TonemapSettings::new(),
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    Temporal,
+    Supersampling,
+}
+
+impl AntiAliasing {
+    pub const ALL: [AntiAliasing; 3] = [AntiAliasing::None, AntiAliasing::Temporal, AntiAliasing::Supersampling];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AntiAliasing::None => "None",
+            AntiAliasing::Temporal => "Temporal",
+            AntiAliasing::Supersampling => "Supersampling",
+        }
+    }
+}
+
+pub struct AntiAliasingSettings {
+    pub mode: AntiAliasing,
+    /// Weight of the current frame when blending into the temporal history.
+    pub temporal_blend: f32,
+    /// Jittered rays traced per pixel in supersampling mode.
+    pub samples: u32,
+}
+
+impl AntiAliasingSettings {
+    pub fn new() -> Self {
+        Self {
+            mode: AntiAliasing::Temporal,
+            temporal_blend: 0.1,
+            samples: 4,
+        }
+    }
+
+    pub fn get_uniform(&self, reset: bool) -> TemporalUniform {
+        TemporalUniform {
+            blend: if reset { 1.0 } else { self.temporal_blend },
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+impl Default for AntiAliasingSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TemporalUniform {
+    blend: f32,
+    _padding: [f32; 3],
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Upscaler {
+    Bilinear,
+    /// Bilinear weights that ignore texels across depth discontinuities.
+    EdgeAware,
+    /// Resolves the jittered low resolution frames into a full resolution history.
+    Temporal,
+}
+
+impl Upscaler {
+    pub const ALL: [Upscaler; 3] = [Upscaler::Bilinear, Upscaler::EdgeAware, Upscaler::Temporal];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Upscaler::Bilinear => "Bilinear",
+            Upscaler::EdgeAware => "Edge aware",
+            Upscaler::Temporal => "Temporal",
+        }
+    }
+}
+
+pub struct ResolutionSettings {
+    /// Fraction of the surface resolution the raytracer runs at.
+    pub scale: f32,
+    pub upscaler: Upscaler,
+    pub dynamic: bool,
+    pub min_scale: f32,
+    pub target_frame_time: f32,
+    average_frame_time: f32,
+    frames_since_change: u32,
+}
+
+impl ResolutionSettings {
+    const SCALE_STEP: f32 = 0.05;
+    const FRAMES_BETWEEN_CHANGES: u32 = 15;
+
+    pub fn new() -> Self {
+        Self {
+            scale: 1.0,
+            upscaler: Upscaler::EdgeAware,
+            dynamic: false,
+            min_scale: 0.25,
+            target_frame_time: 16.6,
+            average_frame_time: 16.6,
+            frames_since_change: 0,
+        }
+    }
+
+    /// Steps the render scale towards the frame time budget, given the last frame time in milliseconds.
+    pub fn update_dynamic(&mut self, frame_time: f32) {
+        self.average_frame_time += (frame_time - self.average_frame_time) * 0.1;
+        self.frames_since_change += 1;
+        if !self.dynamic || self.frames_since_change < Self::FRAMES_BETWEEN_CHANGES {
+            return;
+        }
+
+        let scale = if self.average_frame_time > self.target_frame_time * 1.05 {
+            self.scale - Self::SCALE_STEP
+        } else if self.average_frame_time < self.target_frame_time * 0.8 {
+            self.scale + Self::SCALE_STEP
+        } else {
+            self.scale
+        };
+        let scale = scale.clamp(self.min_scale, 1.0);
+        if scale != self.scale {
+            self.scale = scale;
+            self.frames_since_change = 0;
+        }
+    }
+
+    pub fn render_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = self.scale.clamp(0.1, 1.0);
+        (((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1))
+    }
+}
+
+impl Default for ResolutionSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the raytracer outputs, either the shaded image or one of the debug views of the primary hit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderMode {
+    Lit,
+    Albedo,
+    Normals,
+    Depth,
+    /// Heatmap of the grid cells visited by the primary ray.
+    Steps,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 5] = [
+        RenderMode::Lit,
+        RenderMode::Albedo,
+        RenderMode::Normals,
+        RenderMode::Depth,
+        RenderMode::Steps,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderMode::Lit => "Lit",
+            RenderMode::Albedo => "Albedo",
+            RenderMode::Normals => "Normals",
+            RenderMode::Depth => "Depth",
+            RenderMode::Steps => "Traversal steps",
+        }
+    }
+}
+
+/// Debug views and the built in debug geometry drawn on top of whatever was queued in `DebugDraw`.
+pub struct DebugSettings {
+    pub render_mode: RenderMode,
+    pub world_bounds: bool,
+    pub axes: bool,
+    pub sun_direction: bool,
+    /// Keeps drawing the camera frustum from the moment this was enabled.
+    pub freeze_frustum: bool,
+    frozen_view_projection_inverse: Option<Mat4>,
+}
+
+impl DebugSettings {
+    pub fn new() -> Self {
+        Self {
+            render_mode: RenderMode::Lit,
+            world_bounds: false,
+            axes: false,
+            sun_direction: false,
+            freeze_frustum: false,
+            frozen_view_projection_inverse: None,
+        }
+    }
+
+    pub fn frozen_frustum(&mut self, view_projection: Mat4) -> Option<Mat4> {
+        if !self.freeze_frustum {
+            self.frozen_view_projection_inverse = None;
+            return None;
+        }
+        Some(*self.frozen_view_projection_inverse.get_or_insert(view_projection.inverse()))
+    }
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct FogSettings {
+    pub enabled: bool,
+    pub color: [f32; 3],
+    pub density: f32,
+    /// World height at which `density` applies, fog thins out exponentially above it.
+    pub height: f32,
+    pub height_falloff: f32,
+    /// Henyey-Greenstein anisotropy, positive values scatter light forward.
+    pub anisotropy: f32,
+    pub volumetric: bool,
+    pub volumetric_steps: u32,
+    pub volumetric_strength: f32,
+}
+
+impl FogSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            color: [0.7, 0.75, 0.8],
+            density: 0.02,
+            height: -2.0,
+            height_falloff: 0.15,
+            anisotropy: 0.6,
+            volumetric: false,
+            volumetric_steps: 16,
+            volumetric_strength: 1.0,
+        }
+    }
+
+    pub fn get_uniform(&self) -> FogUniform {
+        FogUniform {
+            color: self.color,
+            density: if self.enabled { self.density } else { 0.0 },
+            height: self.height,
+            height_falloff: self.height_falloff,
+            anisotropy: self.anisotropy,
+            volumetric_strength: if self.volumetric { self.volumetric_strength } else { 0.0 },
+            volumetric_steps: self.volumetric_steps,
+            _padding: [0; 3],
+        }
+    }
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogUniform {
+    color: [f32; 3],
+    density: f32,
+    height: f32,
+    height_falloff: f32,
+    anisotropy: f32,
+    volumetric_strength: f32,
+    volumetric_steps: u32,
+    _padding: [u32; 3],
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TonemapOperator {
+    Aces,
+    Reinhard,
+    AgX,
+}
+
+impl TonemapOperator {
+    pub const ALL: [TonemapOperator; 3] = [TonemapOperator::Aces, TonemapOperator::Reinhard, TonemapOperator::AgX];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TonemapOperator::Aces => "ACES",
+            TonemapOperator::Reinhard => "Reinhard",
+            TonemapOperator::AgX => "AgX",
+        }
+    }
+}
+
+pub struct TonemapSettings {
+    pub operator: TonemapOperator,
+    pub auto_exposure: bool,
+    /// Exposure in stops, used directly in manual mode and as compensation in auto mode.
+    pub exposure: f32,
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    /// How quickly auto exposure adapts to a change in scene luminance, per second.
+    pub adaptation_speed: f32,
+}
+
+impl TonemapSettings {
+    pub fn new() -> Self {
+        Self {
+            operator: TonemapOperator::Aces,
+            auto_exposure: true,
+            exposure: 0.0,
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            adaptation_speed: 1.5,
+        }
+    }
+
+    pub fn get_uniform(&self, delta_time: f32, pixel_count: u32, passthrough: bool) -> TonemapUniform {
+        TonemapUniform {
+            tonemap_operator: self.operator as u32,
+            auto_exposure: self.auto_exposure as u32,
+            exposure: self.exposure,
+            min_log_luminance: self.min_log_luminance,
+            log_luminance_range: (self.max_log_luminance - self.min_log_luminance).max(0.01),
+            adaptation: 1.0 - (-delta_time * self.adaptation_speed).exp(),
+            pixel_count,
+            passthrough: passthrough as u32,
+        }
+    }
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TonemapUniform {
+    tonemap_operator: u32,
+    auto_exposure: u32,
+    exposure: f32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    pixel_count: u32,
+    passthrough: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PostEffect {
+    Bloom,
+    ColorGrading,
+    ChromaticAberration,
+    Vignette,
+    Sharpen,
+}
+
+/// Name, range and default value of a post effect parameter.
+pub struct PostEffectParameter {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+const fn parameter(name: &'static str, min: f32, max: f32, default: f32) -> PostEffectParameter {
+    PostEffectParameter { name, min, max, default }
+}
+
+impl PostEffect {
+    pub const ALL: [PostEffect; 5] = [
+        PostEffect::Bloom,
+        PostEffect::ColorGrading,
+        PostEffect::ChromaticAberration,
+        PostEffect::Vignette,
+        PostEffect::Sharpen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom => "Bloom",
+            PostEffect::ColorGrading => "Color grading",
+            PostEffect::ChromaticAberration => "Chromatic aberration",
+            PostEffect::Vignette => "Vignette",
+            PostEffect::Sharpen => "Sharpen",
+        }
+    }
+
+    pub fn fragment_entry(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom => "fs_bloom",
+            PostEffect::ColorGrading => "fs_color_grading",
+            PostEffect::ChromaticAberration => "fs_chromatic_aberration",
+            PostEffect::Vignette => "fs_vignette",
+            PostEffect::Sharpen => "fs_sharpen",
+        }
+    }
+
+    /// Parameters in the order they are packed into the uniform, at most four.
+    pub fn parameters(&self) -> &'static [PostEffectParameter] {
+        const BLOOM: [PostEffectParameter; 3] = [
+            parameter("Threshold", 0.0, 1.0, 0.8),
+            parameter("Intensity", 0.0, 4.0, 1.0),
+            parameter("Radius", 0.0, 0.1, 0.02),
+        ];
+        const COLOR_GRADING: [PostEffectParameter; 4] = [
+            parameter("Contrast", 0.5, 1.5, 1.0),
+            parameter("Saturation", 0.0, 2.0, 1.0),
+            parameter("Temperature", -1.0, 1.0, 0.0),
+            parameter("Tint", -1.0, 1.0, 0.0),
+        ];
+        const CHROMATIC_ABERRATION: [PostEffectParameter; 1] = [parameter("Strength", 0.0, 0.05, 0.005)];
+        const VIGNETTE: [PostEffectParameter; 2] = [parameter("Intensity", 0.0, 1.0, 0.4), parameter("Smoothness", 0.0, 1.0, 0.6)];
+        const SHARPEN: [PostEffectParameter; 1] = [parameter("Strength", 0.0, 2.0, 0.3)];
+
+        match self {
+            PostEffect::Bloom => &BLOOM,
+            PostEffect::ColorGrading => &COLOR_GRADING,
+            PostEffect::ChromaticAberration => &CHROMATIC_ABERRATION,
+            PostEffect::Vignette => &VIGNETTE,
+            PostEffect::Sharpen => &SHARPEN,
+        }
+    }
+}
+
+pub struct PostProcessNode {
+    pub effect: PostEffect,
+    pub enabled: bool,
+    pub values: [f32; 4],
+}
+
+impl PostProcessNode {
+    pub fn new(effect: PostEffect) -> Self {
+        let mut values = [0.0; 4];
+        for (value, parameter) in values.iter_mut().zip(effect.parameters()) {
+            *value = parameter.default;
+        }
+
+        Self {
+            effect,
+            enabled: matches!(effect, PostEffect::Bloom | PostEffect::Vignette),
+            values,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use glam::{vec2, Vec2};
+
+use crate::GpuContext;
+
+use super::{
+    containers::{BindGroupContainer, BufferContainer, TextureContainer},
+    pipeline_builder::PiplineBuilder,
+    settings::{AntiAliasingSettings, TemporalUniform},
+};
+
+const JITTER_SEQUENCE_LENGTH: u32 = 16;
+
+/// Halton (2, 3) sequence used to jitter the primary rays, in pixels between -0.5 and 0.5.
+pub fn jitter(frame_index: u32) -> Vec2 {
+    let index = frame_index % JITTER_SEQUENCE_LENGTH + 1;
+    vec2(halton(index, 2), halton(index, 3)) - 0.5
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Blends the jittered frame into a reprojected history, writing the result to the resolved texture.
+pub struct TaaPass {
+    bind_groups: BindGroupContainer,
+    buffers: BufferContainer,
+    layout: wgpu::BindGroupLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    reset: bool,
+    sampler: wgpu::Sampler,
+}
+
+impl TaaPass {
+    pub fn new(context: &GpuContext, textures: &TextureContainer, camera_buffer: &wgpu::Buffer, format: wgpu::TextureFormat) -> Self {
+        let mut buffers = BufferContainer::new();
+        buffers.create_uniform_buffer(context, "Temporal buffer", std::mem::size_of::<TemporalUniform>() as u64);
+
+        let layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::unfilterable_texture_binding(),
+                BindGroupContainer::sampler_binding(),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+            ],
+            wgpu::ShaderStages::FRAGMENT,
+            context,
+            "Temporal bind group",
+        );
+
+        let mut pipeline_builder = PiplineBuilder::new();
+        pipeline_builder.set_shader_module("shaders/taa.wgsl", "vs_main", "fs_main");
+        pipeline_builder.set_pixel_format(format);
+        let render_pipeline = pipeline_builder.build(&context.device, &[&layout]);
+
+        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Temporal sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut taa_pass = Self {
+            bind_groups: BindGroupContainer::new(),
+            buffers,
+            layout,
+            render_pipeline,
+            reset: true,
+            sampler,
+        };
+        taa_pass.set_inputs(context, textures, camera_buffer);
+        taa_pass
+    }
+
+    /// Rebinds the render targets, needed whenever they are recreated. Keeps the history, call
+    /// `reset` as well if the history texture was recreated.
+    pub fn set_inputs(&mut self, context: &GpuContext, textures: &TextureContainer, camera_buffer: &wgpu::Buffer) {
+        self.bind_groups.create_bind_group_with_entries(
+            &[
+                wgpu::BindingResource::TextureView(textures.get_view("HDR texture")),
+                wgpu::BindingResource::TextureView(textures.get_view("History texture")),
+                wgpu::BindingResource::TextureView(textures.get_view("Distance texture")),
+                wgpu::BindingResource::Sampler(&self.sampler),
+                camera_buffer.as_entire_binding(),
+                self.buffers.get("Temporal buffer").as_entire_binding(),
+            ],
+            context,
+            "Temporal bind group",
+            &self.layout,
+        );
+    }
+
+    pub fn reset(&mut self) {
+        self.reset = true;
+    }
+
+    /// Whether the next frame blends with the history instead of starting over.
+    pub fn has_history(&self) -> bool {
+        !self.reset
+    }
+
+    pub fn update(&mut self, context: &GpuContext, settings: &AntiAliasingSettings) {
+        context.queue.write_buffer(
+            self.buffers.get("Temporal buffer"),
+            0,
+            bytemuck::cast_slice(&[settings.get_uniform(self.reset)]),
+        );
+        self.reset = false;
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, self.bind_groups.get("Temporal bind group"), &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+    }
+
+    /// Keeps the resolved frame around as next frame's history.
+    pub fn store_history(&self, command_encoder: &mut wgpu::CommandEncoder, textures: &TextureContainer) {
+        let resolved = textures.get_texture("Resolved texture");
+        command_encoder.copy_texture_to_texture(
+            resolved.as_image_copy(),
+            textures.get_texture("History texture").as_image_copy(),
+            resolved.size(),
+        );
+    }
+}
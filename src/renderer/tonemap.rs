@@ -0,0 +1,147 @@
+use crate::GpuContext;
+
+use super::{
+    containers::{BindGroupContainer, BufferContainer, TextureContainer},
+    pipeline_builder::PiplineBuilder,
+    settings::{TonemapSettings, TonemapUniform},
+};
+
+const HISTOGRAM_BINS: u64 = 256;
+/// Textures the tonemapper can read from, the raw HDR frame or its temporally resolved or upscaled version.
+pub const TONEMAP_INPUTS: [&str; 3] = ["HDR texture", "Resolved texture", "Upscaled texture"];
+
+/// Maps the HDR target to the surface, with auto exposure driven by a luminance histogram.
+pub struct TonemapPass {
+    average_pipeline: wgpu::ComputePipeline,
+    bind_groups: BindGroupContainer,
+    buffers: BufferContainer,
+    exposure_layout: wgpu::BindGroupLayout,
+    histogram_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    tonemap_layout: wgpu::BindGroupLayout,
+}
+
+impl TonemapPass {
+    pub fn new(context: &GpuContext, textures: &TextureContainer, output_format: wgpu::TextureFormat) -> Self {
+        let mut buffers = BufferContainer::new();
+        buffers.create_uniform_buffer(context, "Tonemap buffer", std::mem::size_of::<TonemapUniform>() as u64);
+        buffers.create_storage_buffer(context, "Histogram buffer", HISTOGRAM_BINS * 4);
+        buffers.create_storage_buffer_init(bytemuck::cast_slice(&[1.0f32]), context, "Exposure buffer");
+
+        let tonemap_layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::sampler_binding(),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: true }),
+            ],
+            wgpu::ShaderStages::FRAGMENT,
+            context,
+            "Tonemap bind group",
+        );
+        let exposure_layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Uniform),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: false }),
+                BindGroupContainer::buffer_binding(wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+            wgpu::ShaderStages::COMPUTE,
+            context,
+            "Exposure bind group",
+        );
+
+        let mut pipeline_builder = PiplineBuilder::new();
+        pipeline_builder.set_shader_module("shaders/tonemap.wgsl", "vs_main", "fs_main");
+        pipeline_builder.set_pixel_format(output_format);
+        let render_pipeline = pipeline_builder.build(&context.device, &[&tonemap_layout]);
+
+        pipeline_builder.set_compute_shader_module("shaders/exposure.wgsl", "build_histogram");
+        let histogram_pipeline = pipeline_builder.build_compute(&context.device, &[&exposure_layout]);
+        pipeline_builder.set_compute_shader_module("shaders/exposure.wgsl", "average_histogram");
+        let average_pipeline = pipeline_builder.build_compute(&context.device, &[&exposure_layout]);
+
+        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tonemap sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut tonemap_pass = Self {
+            average_pipeline,
+            bind_groups: BindGroupContainer::new(),
+            buffers,
+            exposure_layout,
+            histogram_pipeline,
+            render_pipeline,
+            sampler,
+            tonemap_layout,
+        };
+        tonemap_pass.set_inputs(context, textures);
+        tonemap_pass
+    }
+
+    /// Rebinds the HDR inputs, needed whenever the render targets are recreated.
+    pub fn set_inputs(&mut self, context: &GpuContext, textures: &TextureContainer) {
+        for input in TONEMAP_INPUTS {
+            self.bind_groups.create_bind_group_with_entries(
+                &[
+                    wgpu::BindingResource::TextureView(textures.get_view(input)),
+                    wgpu::BindingResource::Sampler(&self.sampler),
+                    self.buffers.get("Tonemap buffer").as_entire_binding(),
+                    self.buffers.get("Exposure buffer").as_entire_binding(),
+                ],
+                context,
+                &format!("Tonemap {}", input),
+                &self.tonemap_layout,
+            );
+            self.bind_groups.create_bind_group_with_entries(
+                &[
+                    wgpu::BindingResource::TextureView(textures.get_view(input)),
+                    self.buffers.get("Tonemap buffer").as_entire_binding(),
+                    self.buffers.get("Histogram buffer").as_entire_binding(),
+                    self.buffers.get("Exposure buffer").as_entire_binding(),
+                ],
+                context,
+                &format!("Exposure {}", input),
+                &self.exposure_layout,
+            );
+        }
+    }
+
+    pub fn update(&self, context: &GpuContext, settings: &TonemapSettings, delta_time: f32, width: u32, height: u32, passthrough: bool) {
+        context.queue.write_buffer(
+            self.buffers.get("Tonemap buffer"),
+            0,
+            bytemuck::cast_slice(&[settings.get_uniform(delta_time, width * height, passthrough)]),
+        );
+    }
+
+    pub fn compute_exposure(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        input: &str,
+        width: u32,
+        height: u32,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Exposure compute pass"),
+            timestamp_writes,
+        });
+        compute_pass.set_bind_group(0, self.bind_groups.get(&format!("Exposure {}", input)), &[]);
+
+        compute_pass.set_pipeline(&self.histogram_pipeline);
+        compute_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+
+        compute_pass.set_pipeline(&self.average_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, input: &str) {
+        render_pass.set_bind_group(0, self.bind_groups.get(&format!("Tonemap {}", input)), &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::GpuContext;
+
+use super::{
+    containers::{BindGroupContainer, TextureContainer},
+    pipeline_builder::PiplineBuilder,
+    settings::Upscaler,
+};
+
+/// Spatial upsampling of the render resolution HDR frame to the upscaled texture.
+pub struct UpscalePass {
+    bilinear_pipeline: wgpu::RenderPipeline,
+    bind_groups: BindGroupContainer,
+    edge_aware_pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl UpscalePass {
+    pub fn new(context: &GpuContext, textures: &TextureContainer, format: wgpu::TextureFormat) -> Self {
+        let layout = BindGroupContainer::create_layout_with_entries(
+            &[
+                BindGroupContainer::texture_binding(),
+                BindGroupContainer::unfilterable_texture_binding(),
+                BindGroupContainer::sampler_binding(),
+            ],
+            wgpu::ShaderStages::FRAGMENT,
+            context,
+            "Upscale bind group",
+        );
+
+        let mut pipeline_builder = PiplineBuilder::new();
+        pipeline_builder.set_pixel_format(format);
+        pipeline_builder.set_shader_module("shaders/upscale.wgsl", "vs_main", "fs_bilinear");
+        let bilinear_pipeline = pipeline_builder.build(&context.device, &[&layout]);
+        pipeline_builder.set_shader_module("shaders/upscale.wgsl", "vs_main", "fs_edge_aware");
+        let edge_aware_pipeline = pipeline_builder.build(&context.device, &[&layout]);
+
+        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Upscale sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut upscale_pass = Self {
+            bilinear_pipeline,
+            bind_groups: BindGroupContainer::new(),
+            edge_aware_pipeline,
+            layout,
+            sampler,
+        };
+        upscale_pass.set_inputs(context, textures);
+        upscale_pass
+    }
+
+    /// Rebinds the render targets, needed whenever they are recreated.
+    pub fn set_inputs(&mut self, context: &GpuContext, textures: &TextureContainer) {
+        self.bind_groups.create_bind_group_with_entries(
+            &[
+                wgpu::BindingResource::TextureView(textures.get_view("HDR texture")),
+                wgpu::BindingResource::TextureView(textures.get_view("Distance texture")),
+                wgpu::BindingResource::Sampler(&self.sampler),
+            ],
+            context,
+            "Upscale bind group",
+            &self.layout,
+        );
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, upscaler: Upscaler) {
+        render_pass.set_bind_group(0, self.bind_groups.get("Upscale bind group"), &[]);
+        match upscaler {
+            Upscaler::EdgeAware => render_pass.set_pipeline(&self.edge_aware_pipeline),
+            _ => render_pass.set_pipeline(&self.bilinear_pipeline),
+        }
+    }
+}
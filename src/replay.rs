@@ -0,0 +1,265 @@
+use std::{fs, io, path::Path};
+
+use glam::{vec2, vec3, Vec2, Vec3};
+
+use crate::{
+    app::{App, Engine},
+    camera::{Camera, CameraMode, CameraState},
+    input::{Binding, GamepadAxis},
+    invalid_data,
+};
+
+/// Where the settings window saves input recordings.
+pub const INPUT_RECORDING_FILE: &str = "input_recording.txt";
+
+/// An input change from any source, the unit input recordings are made of.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputEvent {
+    Press(Binding),
+    Release(Binding),
+    GamepadAxis(GamepadAxis, f32),
+    GamepadDisconnected,
+    /// Cursor position in physical pixels.
+    CursorMoved(Vec2),
+    Resized(u32, u32),
+    /// Raw mouse movement in pixels.
+    MouseMotion(Vec2),
+    /// Mouse wheel lines, positive away from the user.
+    Scroll(f32),
+}
+
+impl InputEvent {
+    pub fn button(binding: Binding, is_pressed: bool) -> Self {
+        if is_pressed {
+            InputEvent::Press(binding)
+        } else {
+            InputEvent::Release(binding)
+        }
+    }
+
+    fn to_line(self) -> String {
+        match self {
+            InputEvent::Press(binding) => format!("press {}", binding.name()),
+            InputEvent::Release(binding) => format!("release {}", binding.name()),
+            InputEvent::GamepadAxis(axis, value) => format!("axis {:?} {}", axis, value),
+            InputEvent::GamepadDisconnected => String::from("disconnect"),
+            InputEvent::CursorMoved(position) => format!("cursor {} {}", position.x, position.y),
+            InputEvent::Resized(width, height) => format!("resize {} {}", width, height),
+            InputEvent::MouseMotion(delta) => format!("motion {} {}", delta.x, delta.y),
+            InputEvent::Scroll(lines) => format!("scroll {}", lines),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let float = |index: usize| words.get(index)?.parse::<f32>().ok();
+        let integer = |index: usize| words.get(index)?.parse::<u32>().ok();
+        let event = match *words.first()? {
+            "press" => InputEvent::Press(Binding::parse(words.get(1)?)?),
+            "release" => InputEvent::Release(Binding::parse(words.get(1)?)?),
+            "axis" => {
+                let name = *words.get(1)?;
+                let axis = GamepadAxis::ALL.into_iter().find(|axis| format!("{:?}", axis) == name)?;
+                InputEvent::GamepadAxis(axis, float(2)?)
+            }
+            "disconnect" => InputEvent::GamepadDisconnected,
+            "cursor" => InputEvent::CursorMoved(vec2(float(1)?, float(2)?)),
+            "resize" => InputEvent::Resized(integer(1)?, integer(2)?),
+            "motion" => InputEvent::MouseMotion(vec2(float(1)?, float(2)?)),
+            "scroll" => InputEvent::Scroll(float(1)?),
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// The events applied before one frame and how long the frame took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputFrame {
+    pub delta_time: f64,
+    pub events: Vec<InputEvent>,
+}
+
+/// Engine state when a recording started, restored by [`replay`] before the first frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingStart {
+    pub camera: CameraState,
+    /// Frame time the frame timer had not yet spent on fixed updates.
+    pub accumulator: f64,
+    /// Events recreating the held bindings, gamepad axes, window size and cursor position.
+    pub input: Vec<InputEvent>,
+}
+
+impl RecordingStart {
+    pub fn new() -> Self {
+        Self {
+            camera: Camera::new().state(),
+            accumulator: 0.0,
+            input: Vec::new(),
+        }
+    }
+
+    fn save(&self, contents: &mut String) {
+        let camera = &self.camera;
+        contents.push_str(&format!(
+            "camera {} {} {} {} {}\n",
+            camera.mode.name(),
+            vec3_words(camera.position),
+            vec3_words(camera.previous_position),
+            vec3_words(camera.direction),
+            camera.crouching
+        ));
+        contents.push_str(&format!(
+            "body {} {} {} {}\n",
+            vec3_words(camera.body_position),
+            vec3_words(camera.body_velocity),
+            camera.body_height,
+            camera.grounded
+        ));
+        contents.push_str(&format!(
+            "orbit {} {} {} {}\n",
+            vec3_words(camera.orbit_target),
+            camera.orbit_distance,
+            camera.orbit_yaw,
+            camera.orbit_pitch
+        ));
+        contents.push_str(&format!("accumulator {}\n", self.accumulator));
+        for event in &self.input {
+            contents.push_str(&event.to_line());
+            contents.push('\n');
+        }
+    }
+
+    /// Reads a `camera`, `body`, `orbit` or `accumulator` line, `None` if the line is not one.
+    fn parse(&mut self, line: &str) -> Option<()> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let float = |index: usize| words.get(index)?.parse::<f32>().ok();
+        let vector = |index: usize| Some(vec3(float(index)?, float(index + 1)?, float(index + 2)?));
+        let boolean = |index: usize| words.get(index)?.parse::<bool>().ok();
+        let camera = &mut self.camera;
+        match *words.first()? {
+            "camera" => {
+                let name = *words.get(1)?;
+                camera.mode = CameraMode::ALL.into_iter().find(|mode| mode.name() == name)?;
+                camera.position = vector(2)?;
+                camera.previous_position = vector(5)?;
+                camera.direction = vector(8)?;
+                camera.crouching = boolean(11)?;
+            }
+            "body" => {
+                camera.body_position = vector(1)?;
+                camera.body_velocity = vector(4)?;
+                camera.body_height = float(7)?;
+                camera.grounded = boolean(8)?;
+            }
+            "orbit" => {
+                camera.orbit_target = vector(1)?;
+                camera.orbit_distance = float(4)?;
+                camera.orbit_yaw = float(5)?;
+                camera.orbit_pitch = float(6)?;
+            }
+            "accumulator" => self.accumulator = words.get(1)?.parse().ok()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+impl Default for RecordingStart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn vec3_words(vector: Vec3) -> String {
+    format!("{} {} {}", vector.x, vector.y, vector.z)
+}
+
+/// Input captured frame by frame, replayed with [`replay`] to reproduce a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRecording {
+    pub start: RecordingStart,
+    frames: Vec<InputFrame>,
+}
+
+impl InputRecording {
+    pub fn new(start: RecordingStart) -> Self {
+        Self { start, frames: Vec::new() }
+    }
+
+    pub fn push(&mut self, delta_time: f64, events: Vec<InputEvent>) {
+        self.frames.push(InputFrame { delta_time, events });
+    }
+
+    pub fn frames(&self) -> &[InputFrame] {
+        &self.frames
+    }
+
+    /// Total length in seconds.
+    pub fn duration(&self) -> f64 {
+        self.frames.iter().map(|frame| frame.delta_time).sum()
+    }
+
+    /// Writes the start state, then a `frame index delta_time` line per frame followed by one line
+    /// per event.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = String::from("# start state, then frame index delta_time followed by the events applied before it\n");
+        self.start.save(&mut contents);
+        for (index, frame) in self.frames.iter().enumerate() {
+            contents.push_str(&format!("frame {} {}\n", index, frame.delta_time));
+            for event in &frame.events {
+                contents.push_str(&event.to_line());
+                contents.push('\n');
+            }
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut recording = Self::new(RecordingStart::new());
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(frame) = line.strip_prefix("frame ") {
+                let delta_time = frame
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| invalid_data(format!("Line {}: expected `frame index delta_time`", number + 1)))?;
+                recording.push(delta_time, Vec::new());
+                continue;
+            }
+            if recording.frames.is_empty() && recording.start.parse(line).is_some() {
+                continue;
+            }
+            let event = InputEvent::parse(line).ok_or_else(|| invalid_data(format!("Line {}: unknown event {}", number + 1, line)))?;
+            // Events before the first frame recreate the input held when the recording started.
+            match recording.frames.last_mut() {
+                Some(frame) => frame.events.push(event),
+                None => recording.start.input.push(event),
+            }
+        }
+        Ok(recording)
+    }
+}
+
+/// Runs the recording through `engine` and `app` without a window, a frame at a time with the
+/// recorded frame durations, after restoring the camera, frame timer and input from the recording
+/// start. Call `App::init` first like the engine loop does. The result only matches the original
+/// session if the world, app state, input map and settings are the same as when recording.
+pub fn replay(app: &mut impl App, engine: &mut Engine, recording: &InputRecording) {
+    let start = &recording.start;
+    engine.camera.set_state(&start.camera);
+    engine.frame_timer_mut().set_accumulator(start.accumulator);
+    engine.input.restore(&start.input);
+    for frame in recording.frames() {
+        for event in &frame.events {
+            engine.input.apply(*event);
+        }
+        engine.frame_timer_mut().advance(frame.delta_time);
+        engine.step(app);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::invalid_data;
+
+/// Settings file used when `--settings` is not given.
+pub const SETTINGS_FILE: &str = "settings.toml";
+/// Smallest fraction of the window resolution the raytracer runs at.
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+pub const USAGE: &str = "Options:
+    --settings <path>        Settings file to load and save, settings.toml by default
+    --width <pixels>         Window width
+    --height <pixels>        Window height
+    --fullscreen             Start in borderless fullscreen
+    --windowed               Start in a window
+    --present-mode <mode>    vsync, no_vsync, mailbox or immediate
+    --render-scale <scale>   Fraction of the window resolution the raytracer runs at
+    --log-level <filter>     Log filter like RUST_LOG, e.g. error or info
+    --world <path>           World file to load instead of the demo world
+    --help                   Print this message";
+
+/// How frames are presented, `Vsync` is supported everywhere.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    Vsync,
+    NoVsync,
+    Mailbox,
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 4] = [
+        PresentMode::Vsync,
+        PresentMode::NoVsync,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PresentMode::Vsync => "Vsync",
+            PresentMode::NoVsync => "No vsync",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+
+    /// Name used in settings files and on the command line.
+    fn key(&self) -> &'static str {
+        match self {
+            PresentMode::Vsync => "vsync",
+            PresentMode::NoVsync => "no_vsync",
+            PresentMode::Mailbox => "mailbox",
+            PresentMode::Immediate => "immediate",
+        }
+    }
+
+    pub fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::NoVsync => wgpu::PresentMode::AutoNoVsync,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+/// Startup settings, loaded from a TOML file and overridden by command line flags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Window size in logical pixels.
+    pub window_width: u32,
+    pub window_height: u32,
+    pub fullscreen: bool,
+    pub present_mode: PresentMode,
+    /// Fraction of the window resolution the raytracer runs at.
+    pub render_scale: f32,
+    /// Log filter in `RUST_LOG` syntax, the `RUST_LOG` variable still takes precedence.
+    pub log_level: String,
+    /// World file loaded instead of the demo world.
+    pub world: Option<PathBuf>,
+    /// Where the settings were loaded from and are saved to.
+    #[serde(skip)]
+    pub file: PathBuf,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            window_width: 800,
+            window_height: 800,
+            fullscreen: false,
+            present_mode: PresentMode::Vsync,
+            render_scale: 1.0,
+            log_level: String::from("error"),
+            world: None,
+            file: PathBuf::from(SETTINGS_FILE),
+        }
+    }
+
+    /// Loads the settings file named by `--settings`, or the default one, then applies the
+    /// remaining flags on top. A missing settings file is not an error.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let file = match args.iter().position(|arg| arg == "--settings") {
+            Some(index) => PathBuf::from(args.get(index + 1).ok_or("--settings needs a path")?),
+            None => PathBuf::from(SETTINGS_FILE),
+        };
+
+        let mut settings = match Self::load(&file) {
+            Ok(settings) => settings,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Self::new(),
+            Err(error) => return Err(format!("Failed to load {}: {}", file.display(), error)),
+        };
+        settings.file = file;
+
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", flag));
+            match flag.as_str() {
+                "--settings" => {
+                    value()?;
+                }
+                "--width" => settings.window_width = parse(&flag, &value()?, valid_window_size)?,
+                "--height" => settings.window_height = parse(&flag, &value()?, valid_window_size)?,
+                "--fullscreen" => settings.fullscreen = true,
+                "--windowed" => settings.fullscreen = false,
+                "--present-mode" => {
+                    let name = value()?;
+                    settings.present_mode = PresentMode::ALL
+                        .into_iter()
+                        .find(|mode| mode.key() == name)
+                        .ok_or(format!("Unknown present mode {}", name))?;
+                }
+                "--render-scale" => settings.render_scale = parse(&flag, &value()?, valid_render_scale)?,
+                "--log-level" => settings.log_level = value()?,
+                "--world" => settings.world = Some(PathBuf::from(value()?)),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let settings: Self = toml::from_str(&contents).map_err(|error| invalid_data(error.to_string()))?;
+        settings.validate().map_err(invalid_data)?;
+        Ok(settings)
+    }
+
+    /// Rejects values the window or renderer cannot start with, named as in the settings file.
+    fn validate(&self) -> Result<(), String> {
+        let invalid = |value: &dyn std::fmt::Display, name: &str| Err(format!("Invalid value {} for {}", value, name));
+        if !valid_window_size(&self.window_width) {
+            return invalid(&self.window_width, "window_width");
+        }
+        if !valid_window_size(&self.window_height) {
+            return invalid(&self.window_height, "window_height");
+        }
+        if !valid_render_scale(&self.render_scale) {
+            return invalid(&self.render_scale, "render_scale");
+        }
+        Ok(())
+    }
+
+    /// Writes the settings back to the file they were loaded from.
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(|error| invalid_data(error.to_string()))?;
+        fs::write(&self.file, contents)
+    }
+
+    /// Starts logging with `log_level`, call once before anything logs.
+    pub fn init_logger(&self) {
+        let mut builder = pretty_env_logger::formatted_builder();
+        builder.parse_filters(&self.log_level);
+        if let Ok(filters) = std::env::var("RUST_LOG") {
+            builder.parse_filters(&filters);
+        }
+        builder.init();
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str, valid: fn(&T) -> bool) -> Result<T, String> {
+    match value.parse() {
+        Ok(parsed) if valid(&parsed) => Ok(parsed),
+        _ => Err(format!("Invalid value {} for {}", value, flag)),
+    }
+}
+
+fn valid_window_size(size: &u32) -> bool {
+    *size >= 1
+}
+
+/// Also false for NaN.
+fn valid_render_scale(scale: &f32) -> bool {
+    (MIN_RENDER_SCALE..=1.0).contains(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    /// A settings file path unique to the test, removed again if it exists.
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("settings_{}_{}.toml", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn flags_override_the_settings_file() {
+        let path = temp_file("override");
+        fs::write(&path, "window_width = 640\nwindow_height = 480\nfullscreen = true\n").unwrap();
+
+        let settings = Settings::from_args(args(&format!("--settings {} --width 1024 --windowed", path.display()))).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(settings.window_width, 1024);
+        assert_eq!(settings.window_height, 480);
+        assert!(!settings.fullscreen);
+        assert_eq!(settings.file, path);
+    }
+
+    #[test]
+    fn a_missing_settings_file_falls_back_to_the_defaults() {
+        let path = temp_file("missing");
+        let settings = Settings::from_args(args(&format!("--settings {}", path.display()))).unwrap();
+
+        assert_eq!(
+            settings,
+            Settings {
+                file: path,
+                ..Settings::new()
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_arguments_are_rejected() {
+        let missing = temp_file("malformed");
+        let settings = format!("--settings {}", missing.display());
+        assert_eq!(Settings::from_args(args("--settings")).unwrap_err(), "--settings needs a path");
+        assert_eq!(
+            Settings::from_args(args(&format!("{} --nope", settings))).unwrap_err(),
+            "Unknown option --nope"
+        );
+        assert_eq!(
+            Settings::from_args(args(&format!("{} --width", settings))).unwrap_err(),
+            "--width needs a value"
+        );
+        assert_eq!(
+            Settings::from_args(args(&format!("{} --present-mode fast", settings))).unwrap_err(),
+            "Unknown present mode fast"
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let missing = temp_file("range");
+        for (flag, value) in [
+            ("--render-scale", "NaN"),
+            ("--render-scale", "0.1"),
+            ("--render-scale", "2"),
+            ("--width", "0"),
+            ("--height", "-3"),
+        ] {
+            let result = Settings::from_args(args(&format!("--settings {} {} {}", missing.display(), flag, value)));
+            assert_eq!(result.unwrap_err(), format!("Invalid value {} for {}", value, flag));
+        }
+    }
+
+    #[test]
+    fn invalid_settings_files_fail_with_invalid_data() {
+        let path = temp_file("invalid");
+        for contents in ["window_width = 0\n", "render_scale = nan\n", "present_mode = \"fast\"\n"] {
+            fs::write(&path, contents).unwrap();
+            let error = Settings::load(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", contents);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn settings_survive_a_save_and_load() {
+        let path = temp_file("round_trip");
+        let settings = Settings {
+            window_width: 1280,
+            fullscreen: true,
+            present_mode: PresentMode::Mailbox,
+            render_scale: 0.5,
+            world: Some(PathBuf::from("worlds/castle.voxw")),
+            file: path.clone(),
+            ..Settings::new()
+        };
+        settings.save().unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(Settings { file: path, ..loaded }, settings);
+    }
+}
@@ -0,0 +1,43 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+struct Camera {
+    position: vec3<f32>,
+    _padding1: f32,
+    projection_inverse: mat4x4<f32>,
+    view_inverse: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    previous_view_projection: mat4x4<f32>,
+}
+
+struct FrameData {
+    resolution: vec2<f32>,
+    jitter: vec2<f32>,
+    frame_index: u32,
+    samples: u32,
+}
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(0) @binding(1) var<uniform> frame_data: FrameData;
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * vec4<f32>(model.position, 1.0);
+    // Same sub-pixel jitter as the primary rays so the lines resolve with the voxels.
+    out.clip_position += vec4<f32>(frame_data.jitter * out.clip_position.w, 0.0, 0.0);
+    out.color = model.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
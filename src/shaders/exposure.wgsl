@@ -0,0 +1,71 @@
+struct Tonemap {
+    tonemap_operator: u32,
+    auto_exposure: u32,
+    exposure: f32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    pixel_count: u32,
+}
+
+struct Exposure {
+    average_luminance: f32,
+}
+
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> tonemap: Tonemap;
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0) @binding(3) var<storage, read_write> exposure: Exposure;
+
+const BIN_COUNT: u32 = 256u;
+
+var<workgroup> local_histogram: array<atomic<u32>, BIN_COUNT>;
+var<workgroup> weighted_counts: array<f32, BIN_COUNT>;
+
+// Bin 0 collects pixels too dark to register, the rest are spread over the log luminance range.
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    if luminance < 0.0001 {
+        return 0u;
+    }
+    let log_luminance = clamp((log2(luminance) - tonemap.min_log_luminance) / tonemap.log_luminance_range, 0.0, 1.0);
+    return u32(log_luminance * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build_histogram(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+    atomicStore(&local_histogram[local_index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(hdr_texture);
+    if global_id.x < size.x && global_id.y < size.y {
+        let color = textureLoad(hdr_texture, vec2<i32>(global_id.xy), 0).rgb;
+        atomicAdd(&local_histogram[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[local_index], atomicLoad(&local_histogram[local_index]));
+}
+
+@compute @workgroup_size(256)
+fn average_histogram(@builtin(local_invocation_index) local_index: u32) {
+    let count = atomicLoad(&histogram[local_index]);
+    weighted_counts[local_index] = f32(count) * f32(local_index);
+    atomicStore(&histogram[local_index], 0u);
+    workgroupBarrier();
+
+    for (var stride = BIN_COUNT / 2u; stride > 0u; stride >>= 1u) {
+        if local_index < stride {
+            weighted_counts[local_index] += weighted_counts[local_index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if local_index == 0u {
+        let lit_pixels = max(f32(tonemap.pixel_count) - f32(count), 1.0);
+        let average_bin = weighted_counts[0] / lit_pixels - 1.0;
+        let log_luminance = average_bin / 254.0 * tonemap.log_luminance_range + tonemap.min_log_luminance;
+        let target_luminance = exp2(log_luminance);
+        exposure.average_luminance += (target_luminance - exposure.average_luminance) * tonemap.adaptation;
+    }
+}
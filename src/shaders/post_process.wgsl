@@ -0,0 +1,122 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 0.0, 1.0);
+    out.uv = vec2<f32>(model.color.x, 1.0 - model.color.y);
+    return out;
+}
+
+// Meaning of the values depends on the effect, see `PostEffect::parameters`.
+struct PostProcess {
+    values: vec4<f32>,
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+@group(0) @binding(2) var<uniform> post_process: PostProcess;
+@group(0) @binding(3) var lut_texture: texture_3d<f32>;
+
+const BLOOM_SAMPLES: u32 = 24u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+fn texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(input_texture));
+}
+
+@fragment
+fn fs_bloom(in: VertexOutput) -> @location(0) vec4<f32> {
+    let threshold = post_process.values.x;
+    let intensity = post_process.values.y;
+    let radius = post_process.values.z;
+
+    let color = textureSample(input_texture, input_sampler, in.uv).rgb;
+    let aspect = vec2<f32>(texel_size().x / texel_size().y, 1.0);
+
+    // Golden angle spiral gather of the pixels above the threshold.
+    var bloom = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var i = 0u; i < BLOOM_SAMPLES; i++) {
+        let distance = sqrt((f32(i) + 0.5) / f32(BLOOM_SAMPLES));
+        let angle = f32(i) * GOLDEN_ANGLE;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * distance * radius * aspect;
+        let sample = textureSample(input_texture, input_sampler, in.uv + offset).rgb;
+        let weight = 1.0 - distance;
+        bloom += max(sample - vec3<f32>(threshold), vec3<f32>(0.0)) * weight;
+        total_weight += weight;
+    }
+
+    return vec4<f32>(color + bloom / total_weight * intensity, 1.0);
+}
+
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let intensity = post_process.values.x;
+    let smoothness = post_process.values.y;
+
+    let color = textureSample(input_texture, input_sampler, in.uv).rgb;
+    let distance = length(in.uv - 0.5) * 1.41421356;
+    let vignette = 1.0 - intensity * smoothstep(1.0 - smoothness, 1.0, distance);
+
+    return vec4<f32>(color * vignette, 1.0);
+}
+
+@fragment
+fn fs_chromatic_aberration(in: VertexOutput) -> @location(0) vec4<f32> {
+    let strength = post_process.values.x;
+
+    let offset = (in.uv - 0.5) * strength;
+    let red = textureSample(input_texture, input_sampler, in.uv + offset).r;
+    let green = textureSample(input_texture, input_sampler, in.uv).g;
+    let blue = textureSample(input_texture, input_sampler, in.uv - offset).b;
+
+    return vec4<f32>(red, green, blue, 1.0);
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_color_grading(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = clamp(textureSample(input_texture, input_sampler, in.uv).rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    // The LUT is indexed and stored in sRGB so its resolution is spent perceptually evenly.
+    let size = f32(textureDimensions(lut_texture).x);
+    let coordinate = linear_to_srgb(color) * (size - 1.0) / size + 0.5 / size;
+    let graded = textureSampleLevel(lut_texture, input_sampler, coordinate, 0.0).rgb;
+
+    return vec4<f32>(srgb_to_linear(graded), 1.0);
+}
+
+@fragment
+fn fs_sharpen(in: VertexOutput) -> @location(0) vec4<f32> {
+    let strength = post_process.values.x;
+
+    let texel = texel_size();
+    let center = textureSample(input_texture, input_sampler, in.uv).rgb;
+    let neighbours = textureSample(input_texture, input_sampler, in.uv + vec2<f32>(texel.x, 0.0)).rgb
+        + textureSample(input_texture, input_sampler, in.uv - vec2<f32>(texel.x, 0.0)).rgb
+        + textureSample(input_texture, input_sampler, in.uv + vec2<f32>(0.0, texel.y)).rgb
+        + textureSample(input_texture, input_sampler, in.uv - vec2<f32>(0.0, texel.y)).rgb;
+
+    let sharpened = center + (center * 4.0 - neighbours) * strength;
+    return vec4<f32>(max(sharpened, vec3<f32>(0.0)), 1.0);
+}
@@ -21,18 +21,112 @@ struct Camera {
     _padding1: f32,
     projection_inverse: mat4x4<f32>,
     view_inverse: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    previous_view_projection: mat4x4<f32>,
 }
 
-@group(0) @binding(0) var<uniform> frame_data: vec2<f32>;
+struct FrameData {
+    resolution: vec2<f32>,
+    jitter: vec2<f32>,
+    frame_index: u32,
+    samples: u32,
+    render_mode: u32,
+    instance_count: u32,
+}
+
+@group(0) @binding(0) var<uniform> frame_data: FrameData;
 @group(1) @binding(0) var<uniform> camera: Camera;
 
+struct World {
+    origin: vec3<f32>,
+    _padding1: f32,
+    size: vec3<u32>,
+    _padding2: u32,
+}
+
+struct Material {
+    albedo: vec3<f32>,
+    roughness: f32,
+    absorption: vec3<f32>,
+    transparency: f32,
+    ior: f32,
+}
+
+@group(2) @binding(0) var<uniform> world: World;
+@group(2) @binding(1) var<storage, read> voxels: array<u32>;
+@group(2) @binding(2) var<storage, read> materials: array<Material>;
+
+struct Instance {
+    world_to_model: mat4x4<f32>,
+    size: vec3<u32>,
+    // Index of the model's first voxel in `model_voxels`.
+    offset: u32,
+}
+
+@group(2) @binding(3) var<storage, read> instances: array<Instance>;
+@group(2) @binding(4) var<storage, read> model_voxels: array<u32>;
+
+struct Fog {
+    color: vec3<f32>,
+    density: f32,
+    height: f32,
+    height_falloff: f32,
+    anisotropy: f32,
+    volumetric_strength: f32,
+    volumetric_steps: u32,
+}
+
+@group(3) @binding(0) var<uniform> fog: Fog;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) distance: f32,
+    // Depth of the primary hit under the camera projection, so rasterized geometry can be composed on top.
+    @builtin(frag_depth) depth: f32,
+}
+
+fn halton(index: u32, base: u32) -> f32 {
+    var result = 0.0;
+    var fraction = 1.0;
+    var i = index;
+    while i > 0u {
+        fraction /= f32(base);
+        result += fraction * f32(i % base);
+        i /= base;
+    }
+    return result;
+}
+
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let coord = vec2<f32>(in.color) * 2.0 - 1.0; // -1 -> 1
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let coord = vec2<f32>(in.color) * 2.0 - 1.0 + frame_data.jitter; // -1 -> 1
 
-    let color = per_pixel(coord);
+    var out: FragmentOutput;
+    // The depth is rebuilt along the ray that produced `out.distance`, not the pixel center.
+    var primary_ray: Ray;
+    let samples = max(frame_data.samples, 1u);
+    for (var i = 0u; i < samples; i++) {
+        var offset = vec2<f32>(0.0);
+        if samples > 1u {
+            offset = (vec2<f32>(halton(i + 1u, 2u), halton(i + 1u, 3u)) - 0.5) * 2.0 / frame_data.resolution;
+        }
+        let pixel = per_pixel(coord + offset);
+        out.color += vec4<f32>(pixel.color / f32(samples), 0.0);
+        if i == 0u {
+            out.distance = pixel.distance;
+            primary_ray = new_ray(coord + offset);
+        }
+    }
+    out.color.a = 1.0;
+
+    out.depth = 1.0;
+    if out.distance < FOG_FAR {
+        let position = primary_ray.origin + normalize(primary_ray.direction) * out.distance;
+        let clip = camera.view_projection * vec4<f32>(position, 1.0);
+        out.depth = clamp(clip.z / clip.w, 0.0, 1.0);
+    }
 
-    return color;
+    return out;
 }
 
 struct Ray {
@@ -40,58 +134,359 @@ struct Ray {
     direction: vec3<f32>
 }
 
+// Rays start on the near plane and head for the far plane, which makes them parallel under an
+// orthographic projection.
 fn new_ray(coord: vec2<f32>) -> Ray {
     var ray: Ray;
 
-    let cameraTarget = camera.projection_inverse * vec4<f32>(coord.x, coord.y, 1.0, 1.0);
-    let t = normalize((cameraTarget.xyz / cameraTarget.w));
-    let ray_direction = (camera.view_inverse * vec4<f32>(t.x, t.y, t.z, 0.0)).xyz;
+    let near = camera.projection_inverse * vec4<f32>(coord.x, coord.y, 0.0, 1.0);
+    let far = camera.projection_inverse * vec4<f32>(coord.x, coord.y, 1.0, 1.0);
+    let near_point = near.xyz / near.w;
+    let t = normalize(far.xyz / far.w - near_point);
 
-    ray.direction = ray_direction;
-    ray.origin = camera.position;
+    ray.direction = (camera.view_inverse * vec4<f32>(t, 0.0)).xyz;
+    ray.origin = (camera.view_inverse * vec4<f32>(near_point, 1.0)).xyz;
     return ray;
 }
 
-struct Sphere {
-    albedo: vec3<f32>,
+const AIR: u32 = 0u;
+const MAX_STEPS: u32 = 256u;
+const MAX_DEPTH: u32 = 4u;
+const STACK_SIZE: u32 = 8u;
+const EPSILON: f32 = 0.001;
+const SUN_DIRECTION: vec3<f32> = vec3<f32>(0.4, 0.8, 0.3);
+const FOG_FAR: f32 = 1000.0;
+const PI: f32 = 3.14159265;
+
+const RENDER_LIT: u32 = 0u;
+const RENDER_ALBEDO: u32 = 1u;
+const RENDER_NORMALS: u32 = 2u;
+const RENDER_DEPTH: u32 = 3u;
+const RENDER_STEPS: u32 = 4u;
+
+// Grid id of the static world, every other id is the index of an entity instance.
+const WORLD_GRID: u32 = 0xffffffffu;
+
+fn grid_voxel(grid: u32, size: vec3<i32>, cell: vec3<i32>) -> u32 {
+    if any(cell < vec3<i32>(0)) || any(cell >= size) {
+        return AIR;
+    }
+    let index = cell.x + cell.y * size.x + cell.z * size.x * size.y;
+    if grid == WORLD_GRID {
+        return voxels[index];
+    }
+    return model_voxels[instances[grid].offset + u32(index)];
+}
+
+fn voxel_at(cell: vec3<i32>) -> u32 {
+    return grid_voxel(WORLD_GRID, vec3<i32>(world.size), cell);
+}
+
+struct Hit {
+    hit: bool,
+    t: f32,
     position: vec3<f32>,
-    radius: f32,
+    normal: vec3<f32>,
+    material: u32,
+    // Grid cells visited before the ray hit or left the world.
+    steps: u32,
+    // Entity models are shaded as opaque, rays do not travel through them.
+    entity: bool,
 }
 
-fn new_sphere() -> Sphere {
-    var sphere: Sphere;
-    sphere.albedo = vec3<f32>(1.0, 0.0, 1.0);
-    sphere.position = vec3<f32>(0.0);
-    sphere.radius = 0.5;
-    return sphere;
+// Walks a grid with a DDA until the ray leaves `medium`, i.e. reaches a voxel of any other material.
+// `origin` is in the grid's voxel space, `position` is left for the caller to fill in.
+fn trace_grid(grid: u32, grid_size: vec3<u32>, origin: vec3<f32>, direction: vec3<f32>, medium: u32) -> Hit {
+    var hit: Hit;
+    hit.hit = false;
+
+    let safe_direction = select(direction, vec3<f32>(EPSILON * EPSILON), abs(direction) < vec3<f32>(EPSILON * EPSILON));
+    let inverse_direction = 1.0 / safe_direction;
+    let local_origin = origin;
+    let size = vec3<f32>(grid_size);
+
+    let t0 = -local_origin * inverse_direction;
+    let t1 = (size - local_origin) * inverse_direction;
+    let t_min = min(t0, t1);
+    let t_max = max(t0, t1);
+    let t_exit = min(min(t_max.x, t_max.y), t_max.z);
+    var t = max(max(max(t_min.x, t_min.y), t_min.z), 0.0);
+    if t_exit < t {
+        return hit;
+    }
+
+    var normal = vec3<f32>(0.0);
+    if t > 0.0 {
+        normal = -sign(safe_direction) * vec3<f32>(t_min >= vec3<f32>(t));
+    }
+
+    let step = vec3<i32>(sign(safe_direction));
+    let delta = abs(inverse_direction);
+    var cell = clamp(vec3<i32>(floor(local_origin + safe_direction * t)), vec3<i32>(0), vec3<i32>(grid_size) - 1);
+    var side = (vec3<f32>(cell) + max(vec3<f32>(step), vec3<f32>(0.0)) - local_origin) * inverse_direction;
+
+    for (var i = 0u; i < MAX_STEPS; i++) {
+        hit.steps = i;
+        if any(cell < vec3<i32>(0)) || any(cell >= vec3<i32>(grid_size)) {
+            break;
+        }
+
+        let material = grid_voxel(grid, vec3<i32>(grid_size), cell);
+        if material != medium {
+            hit.hit = true;
+            hit.t = t;
+            hit.normal = normal;
+            hit.material = material;
+            return hit;
+        }
+
+        if side.x < side.y && side.x < side.z {
+            t = side.x;
+            side.x += delta.x;
+            cell.x += step.x;
+            normal = vec3<f32>(-f32(step.x), 0.0, 0.0);
+        } else if side.y < side.z {
+            t = side.y;
+            side.y += delta.y;
+            cell.y += step.y;
+            normal = vec3<f32>(0.0, -f32(step.y), 0.0);
+        } else {
+            t = side.z;
+            side.z += delta.z;
+            cell.z += step.z;
+            normal = vec3<f32>(0.0, 0.0, -f32(step.z));
+        }
+    }
+
+    // Rays leaving the grid from inside a medium exit into open air.
+    if medium != AIR {
+        hit.hit = true;
+        hit.t = t;
+        hit.normal = normal;
+        hit.material = AIR;
+    }
+    return hit;
 }
 
-fn per_pixel(coord: vec2<f32>) -> vec4<f32> {
-    let ray = new_ray(coord);
-    let sphere = new_sphere();
+// Closest hit against the static world and, for rays through air, every entity model.
+fn trace(origin: vec3<f32>, direction: vec3<f32>, medium: u32) -> Hit {
+    var hit = trace_grid(WORLD_GRID, world.size, origin - world.origin, direction, medium);
+    var steps = hit.steps;
 
-    let origin = ray.origin - sphere.position;
+    if medium == AIR {
+        for (var i = 0u; i < frame_data.instance_count; i++) {
+            let instance = instances[i];
+            let local_origin = (instance.world_to_model * vec4<f32>(origin, 1.0)).xyz;
+            // Left unnormalized so distances along it match the world space ray.
+            let local_direction = (instance.world_to_model * vec4<f32>(direction, 0.0)).xyz;
+            var entity_hit = trace_grid(i, instance.size, local_origin, local_direction, AIR);
+            steps += entity_hit.steps;
+            if entity_hit.hit && (!hit.hit || entity_hit.t < hit.t) {
+                entity_hit.normal = normalize((transpose(instance.world_to_model) * vec4<f32>(entity_hit.normal, 0.0)).xyz);
+                entity_hit.entity = true;
+                hit = entity_hit;
+            }
+        }
+    }
+
+    hit.steps = steps;
+    hit.position = origin + direction * hit.t;
+    return hit;
+}
+
+fn sky(direction: vec3<f32>) -> vec3<f32> {
+    let height = clamp(direction.y * 0.5 + 0.5, 0.0, 1.0);
+    return mix(vec3<f32>(0.8, 0.85, 0.9), vec3<f32>(0.35, 0.55, 0.9), height);
+}
+
+fn shade(hit: Hit, material: Material) -> vec3<f32> {
+    let sun = normalize(SUN_DIRECTION);
+    let shadow_hit = trace(hit.position + hit.normal * EPSILON, sun, AIR);
+    var shadow = 1.0;
+    if shadow_hit.hit {
+        shadow = select(materials[shadow_hit.material].transparency, 0.0, shadow_hit.entity);
+    }
+    let diffuse = max(dot(hit.normal, sun), 0.0) * shadow;
+    return material.albedo * (diffuse + 0.2);
+}
+
+fn fresnel(cos_theta: f32, n1: f32, n2: f32) -> f32 {
+    let r0 = pow((n1 - n2) / (n1 + n2), 2.0);
+    return r0 + (1.0 - r0) * pow(1.0 - cos_theta, 5.0);
+}
+
+fn fog_density(position: vec3<f32>) -> f32 {
+    return fog.density * exp(-fog.height_falloff * (position.y - fog.height));
+}
+
+// Integral of the exponential height fog density along the ray over `distance`.
+fn fog_optical_depth(origin: vec3<f32>, direction: vec3<f32>, distance: f32) -> f32 {
+    let falloff = fog.height_falloff * direction.y;
+    if abs(falloff) < EPSILON {
+        return fog_density(origin) * distance;
+    }
+    return fog_density(origin) * (1.0 - exp(-falloff * distance)) / falloff;
+}
+
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    return (1.0 - g * g) / (4.0 * PI * pow(denominator, 1.5));
+}
+
+struct FogSegment {
+    transmittance: f32,
+    inscatter: vec3<f32>,
+}
+
+fn apply_fog(origin: vec3<f32>, direction: vec3<f32>, distance: f32, volumetric: bool) -> FogSegment {
+    var segment: FogSegment;
+    segment.transmittance = exp(-fog_optical_depth(origin, direction, distance));
+
+    let sun = normalize(SUN_DIRECTION);
+    // Normalised so isotropic scattering of unshadowed sunlight matches the fog color.
+    let phase = henyey_greenstein(dot(direction, sun), fog.anisotropy) * 4.0 * PI;
+    segment.inscatter = fog.color * (1.0 - segment.transmittance) * 0.3;
+
+    if !volumetric || fog.volumetric_strength <= 0.0 {
+        segment.inscatter += fog.color * (1.0 - segment.transmittance) * phase;
+        return segment;
+    }
+
+    // Ray march shadowed in-scattering towards the sun through the voxel grid.
+    let steps = max(fog.volumetric_steps, 1u);
+    let step_length = min(distance, 64.0) / f32(steps);
+    var shafts = 0.0;
+    for (var i = 0u; i < steps; i++) {
+        let t = (f32(i) + 0.5) * step_length;
+        let position = origin + direction * t;
+        if trace(position, sun, AIR).hit {
+            continue;
+        }
+        shafts += fog_density(position) * exp(-fog_optical_depth(origin, direction, t)) * step_length;
+    }
+    segment.inscatter += fog.color * shafts * phase * fog.volumetric_strength;
+
+    return segment;
+}
+
+struct RayTask {
+    origin: vec3<f32>,
+    direction: vec3<f32>,
+    throughput: vec3<f32>,
+    medium: u32,
+    depth: u32,
+}
+
+struct Pixel {
+    color: vec3<f32>,
+    // Distance to the primary hit along the normalised ray.
+    distance: f32,
+}
+
+// Blue through green to red as `value` goes from 0 to 1.
+fn heatmap(value: f32) -> vec3<f32> {
+    let x = clamp(value, 0.0, 1.0);
+    return clamp(vec3<f32>(2.0 * x - 0.5, 1.5 - abs(2.0 * x - 1.0) * 2.0, 1.5 - 2.0 * x), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Unshaded view of the primary hit, for checking the traversal rather than the lighting.
+fn debug_pixel(ray: Ray) -> Pixel {
+    var pixel: Pixel;
+    pixel.distance = FOG_FAR;
+
+    let direction = normalize(ray.direction);
+    let hit = trace(ray.origin, direction, voxel_at(vec3<i32>(floor(ray.origin - world.origin))));
+    if hit.hit {
+        pixel.distance = hit.t;
+    }
 
-    let a = dot(ray.direction, ray.direction);
-    let b = 2.0 * dot(origin, ray.direction);
-    let c = dot(origin, origin) - pow(sphere.radius, 2.0);
+    switch frame_data.render_mode {
+        case RENDER_ALBEDO: {
+            pixel.color = select(vec3<f32>(0.0), materials[hit.material].albedo, hit.hit);
+        }
+        case RENDER_NORMALS: {
+            pixel.color = select(vec3<f32>(0.0), hit.normal * 0.5 + 0.5, hit.hit);
+        }
+        case RENDER_DEPTH: {
+            let range = length(vec3<f32>(world.size));
+            pixel.color = select(vec3<f32>(0.0), vec3<f32>(1.0 - hit.t / range), hit.hit);
+        }
+        default: {
+            pixel.color = heatmap(f32(hit.steps) / f32(MAX_STEPS));
+        }
+    }
+    return pixel;
+}
 
-    let discriminant = b * b - 4.0 * a * c;
-    if discriminant < 0.0 {
-        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+fn per_pixel(coord: vec2<f32>) -> Pixel {
+    let ray = new_ray(coord);
+    if frame_data.render_mode != RENDER_LIT {
+        return debug_pixel(ray);
     }
 
-    let closestT = (-b - sqrt(discriminant)) / (2.0 * a);
-    let t0 = (-b + sqrt(discriminant)) / (2.0 * a);
+    var pixel: Pixel;
+    pixel.distance = FOG_FAR;
+
+    var stack: array<RayTask, STACK_SIZE>;
+    stack[0] = RayTask(ray.origin, normalize(ray.direction), vec3<f32>(1.0), voxel_at(vec3<i32>(floor(ray.origin - world.origin))), 0u);
+    var count = 1u;
+    var color = vec3<f32>(0.0);
+
+    while count > 0u {
+        count--;
+        let task = stack[count];
+        let medium = materials[task.medium];
 
-    let hit_point = origin + ray.direction * closestT;
-    let hit_normal = normalize(hit_point);
+        let hit = trace(task.origin, task.direction, task.medium);
+        if task.depth == 0u && hit.hit {
+            pixel.distance = hit.t;
+        }
 
-    let light_dir = normalize(vec3<f32>(0.0, 0.0, -1.0));
-    let light_intensity = max(dot(hit_normal, -light_dir), 0.0);
+        var fogged_throughput = task.throughput;
+        if task.medium == AIR && fog.density > 0.0 {
+            let segment = apply_fog(task.origin, task.direction, select(FOG_FAR, hit.t, hit.hit), task.depth == 0u);
+            color += task.throughput * segment.inscatter;
+            fogged_throughput *= segment.transmittance;
+        }
 
-    var sphere_color = sphere.albedo;
-    sphere_color *= light_intensity;
+        if !hit.hit {
+            color += fogged_throughput * sky(task.direction);
+            continue;
+        }
 
-    return vec4<f32>(sphere_color, 1.0);
-}
\ No newline at end of file
+        // Tinted absorption through the volume the ray just crossed.
+        let throughput = fogged_throughput * exp(-medium.absorption * hit.t);
+        var material = materials[hit.material];
+        if hit.entity {
+            material.transparency = 0.0;
+        }
+
+        let cos_theta = clamp(-dot(task.direction, hit.normal), 0.0, 1.0);
+        let refracted = refract(task.direction, hit.normal, medium.ior / material.ior);
+        var reflectance = fresnel(cos_theta, medium.ior, material.ior);
+        if all(refracted == vec3<f32>(0.0)) {
+            reflectance = 1.0;
+        }
+
+        let glossiness = mix(1.0 - material.roughness, 1.0, material.transparency);
+        let specular = reflectance * glossiness;
+        let transmission = (1.0 - reflectance) * material.transparency;
+        color += throughput * (1.0 - material.transparency) * (1.0 - specular) * shade(hit, material);
+
+        if task.depth >= MAX_DEPTH {
+            continue;
+        }
+        if specular > EPSILON && count < STACK_SIZE {
+            let direction = reflect(task.direction, hit.normal);
+            stack[count] = RayTask(hit.position + hit.normal * EPSILON, direction, throughput * specular, task.medium, task.depth + 1u);
+            count++;
+        }
+        if transmission > EPSILON && count < STACK_SIZE {
+            stack[count] = RayTask(hit.position - hit.normal * EPSILON, refracted, throughput * transmission, hit.material, task.depth + 1u);
+            count++;
+        }
+    }
+
+    pixel.color = color;
+    return pixel;
+}
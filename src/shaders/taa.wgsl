@@ -0,0 +1,94 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 0.0, 1.0);
+    out.uv = vec2<f32>(model.color.x, 1.0 - model.color.y);
+    return out;
+}
+
+struct Camera {
+    position: vec3<f32>,
+    _padding1: f32,
+    projection_inverse: mat4x4<f32>,
+    view_inverse: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    previous_view_projection: mat4x4<f32>,
+}
+
+struct Temporal {
+    blend: f32,
+}
+
+@group(0) @binding(0) var current_texture: texture_2d<f32>;
+@group(0) @binding(1) var history_texture: texture_2d<f32>;
+@group(0) @binding(2) var distance_texture: texture_2d<f32>;
+@group(0) @binding(3) var linear_sampler: sampler;
+@group(0) @binding(4) var<uniform> camera: Camera;
+@group(0) @binding(5) var<uniform> temporal: Temporal;
+
+fn rgb_to_ycocg(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        dot(color, vec3<f32>(0.25, 0.5, 0.25)),
+        dot(color, vec3<f32>(0.5, 0.0, -0.5)),
+        dot(color, vec3<f32>(-0.25, 0.5, -0.25)),
+    );
+}
+
+fn ycocg_to_rgb(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(color.x + color.y - color.z, color.x + color.z, color.x - color.y - color.z);
+}
+
+// Reconstructs the world position of the primary hit and projects it with last frame's camera.
+fn reproject(uv: vec2<f32>, distance: f32) -> vec2<f32> {
+    let coord = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let near = camera.projection_inverse * vec4<f32>(coord, 0.0, 1.0);
+    let far = camera.projection_inverse * vec4<f32>(coord, 1.0, 1.0);
+    let near_point = near.xyz / near.w;
+    let direction = (camera.view_inverse * vec4<f32>(normalize(far.xyz / far.w - near_point), 0.0)).xyz;
+    let position = (camera.view_inverse * vec4<f32>(near_point, 1.0)).xyz + direction * distance;
+
+    let previous = camera.previous_view_projection * vec4<f32>(position, 1.0);
+    let previous_coord = previous.xy / previous.w;
+    return vec2<f32>(previous_coord.x * 0.5 + 0.5, 0.5 - previous_coord.y * 0.5);
+}
+
+// Runs at output resolution, so with a render scale below one it doubles as a temporal upscaler.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(current_texture));
+    let pixel = clamp(vec2<i32>(in.uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    let current = textureSampleLevel(current_texture, linear_sampler, in.uv, 0.0).rgb;
+
+    // Colour bounds of the 3x3 neighbourhood, history outside of them is stale.
+    var minimum = vec3<f32>(1e10);
+    var maximum = vec3<f32>(-1e10);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let color = rgb_to_ycocg(textureLoad(current_texture, neighbour, 0).rgb);
+            minimum = min(minimum, color);
+            maximum = max(maximum, color);
+        }
+    }
+
+    let distance = textureLoad(distance_texture, pixel, 0).r;
+    let history_uv = reproject(in.uv, distance);
+    if any(history_uv < vec2<f32>(0.0)) || any(history_uv > vec2<f32>(1.0)) {
+        return vec4<f32>(current, 1.0);
+    }
+
+    let history = textureSampleLevel(history_texture, linear_sampler, history_uv, 0.0).rgb;
+    let clamped = ycocg_to_rgb(clamp(rgb_to_ycocg(history), minimum, maximum));
+
+    return vec4<f32>(mix(clamped, current, temporal.blend), 1.0);
+}
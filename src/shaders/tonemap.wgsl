@@ -0,0 +1,112 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 0.0, 1.0);
+    out.uv = vec2<f32>(model.color.x, 1.0 - model.color.y);
+    return out;
+}
+
+struct Tonemap {
+    tonemap_operator: u32,
+    auto_exposure: u32,
+    exposure: f32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    pixel_count: u32,
+    passthrough: u32,
+}
+
+struct Exposure {
+    average_luminance: f32,
+}
+
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var hdr_sampler: sampler;
+@group(0) @binding(2) var<uniform> tonemap: Tonemap;
+@group(0) @binding(3) var<storage, read> exposure: Exposure;
+
+const ACES: u32 = 0u;
+const REINHARD: u32 = 1u;
+const AGX: u32 = 2u;
+
+// Narkowicz's fit of the ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return color / (1.0 + luminance);
+}
+
+// Polynomial approximation of the AgX base curve by Benjamin Wrensch.
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var encoded = inset * max(color, vec3<f32>(1e-10));
+    encoded = clamp((log2(encoded) - min_ev) / (max_ev - min_ev), vec3<f32>(0.0), vec3<f32>(1.0));
+    encoded = agx_contrast(encoded);
+    // The curve outputs display encoded values, the surface expects linear color.
+    return pow(max(outset * encoded, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(hdr_texture, hdr_sampler, in.uv).rgb;
+    // Debug views are already display ready.
+    if tonemap.passthrough != 0u {
+        return vec4<f32>(hdr, 1.0);
+    }
+
+    var scale = exp2(tonemap.exposure);
+    if tonemap.auto_exposure != 0u {
+        // Map the average luminance to middle grey.
+        scale *= 0.18 / max(exposure.average_luminance, 0.0001);
+    }
+    let color = hdr * scale;
+
+    switch tonemap.tonemap_operator {
+        case REINHARD: {
+            return vec4<f32>(reinhard(color), 1.0);
+        }
+        case AGX: {
+            return vec4<f32>(agx(color), 1.0);
+        }
+        default: {
+            return vec4<f32>(aces(color), 1.0);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 0.0, 1.0);
+    out.uv = vec2<f32>(model.color.x, 1.0 - model.color.y);
+    return out;
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var distance_texture: texture_2d<f32>;
+@group(0) @binding(2) var linear_sampler: sampler;
+
+@fragment
+fn fs_bilinear(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSampleLevel(input_texture, linear_sampler, in.uv, 0.0).rgb, 1.0);
+}
+
+// Joint bilateral upsampling guided by the primary hit distance, so silhouettes stay sharp.
+@fragment
+fn fs_edge_aware(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(input_texture));
+    let position = in.uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let fraction = fract(position);
+
+    let nearest = clamp(vec2<i32>(round(position)), vec2<i32>(0), size - 1);
+    let reference = textureLoad(distance_texture, nearest, 0).r;
+
+    var color = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var y = 0; y <= 1; y++) {
+        for (var x = 0; x <= 1; x++) {
+            let texel = clamp(base + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let bilinear = mix(1.0 - fraction.x, fraction.x, f32(x)) * mix(1.0 - fraction.y, fraction.y, f32(y));
+            let distance = textureLoad(distance_texture, texel, 0).r;
+            let similarity = 1.0 / (0.01 + abs(distance - reference) / max(reference, 0.01));
+            let weight = bilinear * similarity;
+            color += textureLoad(input_texture, texel, 0).rgb * weight;
+            total_weight += weight;
+        }
+    }
+
+    return vec4<f32>(color / max(total_weight, 0.0001), 1.0);
+}
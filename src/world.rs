@@ -0,0 +1,275 @@
+use std::{fs, io, path::Path};
+
+use glam::{ivec3, uvec3, vec3, IVec3, UVec3, Vec3};
+
+use crate::invalid_data;
+
+/// First bytes of a world file, followed by the format version.
+const WORLD_FILE_MAGIC: &[u8; 4] = b"VOXW";
+const WORLD_FILE_VERSION: u32 = 1;
+
+/// Material id of empty space. Index 0 of the material table is always air.
+pub const AIR: u32 = 0;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Material {
+    pub albedo: [f32; 3],
+    pub roughness: f32,
+    /// Beer-Lambert absorption coefficient per unit of distance travelled inside the material.
+    pub absorption: [f32; 3],
+    pub transparency: f32,
+    pub ior: f32,
+    _padding: [f32; 3],
+}
+
+impl Material {
+    pub fn opaque(albedo: Vec3, roughness: f32) -> Self {
+        Self {
+            albedo: albedo.into(),
+            roughness,
+            absorption: [0.0; 3],
+            transparency: 0.0,
+            ior: 1.5,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn transparent(albedo: Vec3, absorption: Vec3, roughness: f32, ior: f32) -> Self {
+        Self {
+            albedo: albedo.into(),
+            roughness,
+            absorption: absorption.into(),
+            transparency: 1.0,
+            ior,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn air() -> Self {
+        Self::transparent(Vec3::ZERO, Vec3::ZERO, 0.0, 1.0)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WorldUniform {
+    origin: [f32; 3],
+    _padding1: f32,
+    size: [u32; 3],
+    _padding2: u32,
+}
+
+pub struct VoxelWorld {
+    materials: Vec<Material>,
+    origin: Vec3,
+    /// Whether each material blocks movement, parallel to `materials`.
+    solid: Vec<bool>,
+    size: UVec3,
+    voxels: Vec<u32>,
+}
+
+impl VoxelWorld {
+    pub fn new(size: UVec3, origin: Vec3) -> Self {
+        Self {
+            materials: vec![Material::air()],
+            origin,
+            solid: vec![false],
+            size,
+            voxels: vec![AIR; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    /// Small scene with a stone floor, a pool of water and a glass block.
+    pub fn demo() -> Self {
+        let mut world = Self::new(uvec3(32, 16, 32), vec3(-16.0, -4.0, -16.0));
+
+        let stone = world.add_material(Material::opaque(vec3(0.55, 0.55, 0.5), 0.9));
+        let polished = world.add_material(Material::opaque(vec3(0.8, 0.3, 0.2), 0.2));
+        let water = world.add_material(Material::transparent(vec3(0.6, 0.8, 0.9), vec3(0.45, 0.12, 0.08), 0.0, 1.33));
+        let glass = world.add_material(Material::transparent(vec3(0.9, 0.95, 1.0), vec3(0.02, 0.02, 0.01), 0.0, 1.5));
+
+        world.set_solid(water, false);
+
+        world.fill(ivec3(0, 0, 0), ivec3(32, 2, 32), stone);
+        world.fill(ivec3(6, 1, 6), ivec3(18, 2, 18), water);
+        world.fill(ivec3(20, 2, 8), ivec3(24, 6, 12), glass);
+        world.fill(ivec3(10, 2, 22), ivec3(12, 8, 24), polished);
+
+        world
+    }
+
+    /// Adds a material, solid unless changed with [`VoxelWorld::set_solid`].
+    pub fn add_material(&mut self, material: Material) -> u32 {
+        self.materials.push(material);
+        self.solid.push(true);
+        self.materials.len() as u32 - 1
+    }
+
+    /// Marks a material as blocking movement or not, e.g. for liquids that can be swum through.
+    pub fn set_solid(&mut self, material: u32, solid: bool) {
+        if let Some(entry) = self.solid.get_mut(material as usize) {
+            *entry = solid;
+        }
+    }
+
+    pub fn is_material_solid(&self, material: u32) -> bool {
+        self.solid.get(material as usize).copied().unwrap_or(false)
+    }
+
+    pub fn contains(&self, position: IVec3) -> bool {
+        position.cmpge(IVec3::ZERO).all() && position.cmplt(self.size.as_ivec3()).all()
+    }
+
+    /// Material id at a grid position, or [`AIR`] outside of the grid.
+    pub fn get(&self, position: IVec3) -> u32 {
+        match self.index(position) {
+            Some(index) => self.voxels[index],
+            None => AIR,
+        }
+    }
+
+    pub fn set(&mut self, position: IVec3, material: u32) {
+        if let Some(index) = self.index(position) {
+            self.voxels[index] = material;
+        }
+    }
+
+    /// Sets every voxel in the half open box `min..max`.
+    pub fn fill(&mut self, min: IVec3, max: IVec3, material: u32) {
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    self.set(ivec3(x, y, z), material);
+                }
+            }
+        }
+    }
+
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    pub fn voxels(&self) -> &[u32] {
+        &self.voxels
+    }
+
+    /// Writes the grid, materials and solidity to a binary file in native byte order.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(WORLD_FILE_MAGIC);
+        bytes.extend_from_slice(bytemuck::bytes_of(&WORLD_FILE_VERSION));
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.size.to_array()));
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.origin.to_array()));
+        bytes.extend_from_slice(bytemuck::bytes_of(&(self.materials.len() as u32)));
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.materials));
+        bytes.extend(self.solid.iter().map(|&solid| solid as u8));
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.voxels));
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = ByteReader { bytes: &bytes };
+        if reader.take(4)? != WORLD_FILE_MAGIC {
+            return Err(invalid_data("not a world file"));
+        }
+        let version: u32 = reader.read()?;
+        if version != WORLD_FILE_VERSION {
+            return Err(invalid_data("unsupported world file version"));
+        }
+        let size = uvec3(reader.read()?, reader.read()?, reader.read()?);
+        let origin = vec3(reader.read()?, reader.read()?, reader.read()?);
+        if !origin.is_finite() {
+            return Err(invalid_data("world origin is not finite"));
+        }
+        // Voxel indices are computed in u32 and positions in i32, so the whole grid has to fit both.
+        if size.max_element() > i32::MAX as u32 {
+            return Err(invalid_data("world is too large"));
+        }
+        let volume = size
+            .x
+            .checked_mul(size.y)
+            .and_then(|area| area.checked_mul(size.z))
+            .ok_or_else(|| invalid_data("world is too large"))?;
+        if volume == 0 {
+            return Err(invalid_data("world is empty"));
+        }
+        let material_count: u32 = reader.read()?;
+        // Material 0 is air, and the GPU needs at least one material to bind.
+        if material_count == 0 {
+            return Err(invalid_data("world has no materials"));
+        }
+        let materials = reader.read_slice::<Material>(material_count as usize)?;
+        let solid = reader.take(material_count as usize)?.iter().map(|&solid| solid != 0).collect();
+        let voxels = reader.read_slice::<u32>(volume as usize)?;
+        // The shader indexes the materials with the voxel ids unchecked.
+        if voxels.iter().any(|&voxel| voxel >= material_count) {
+            return Err(invalid_data("voxel uses a material that does not exist"));
+        }
+
+        Ok(Self {
+            materials,
+            origin,
+            solid,
+            size,
+            voxels,
+        })
+    }
+
+    pub fn get_uniform(&self) -> WorldUniform {
+        WorldUniform {
+            origin: self.origin.into(),
+            _padding1: 0.0,
+            size: self.size.into(),
+            _padding2: 0,
+        }
+    }
+
+    fn index(&self, position: IVec3) -> Option<usize> {
+        if !self.contains(position) {
+            return None;
+        }
+        let position = position.as_uvec3();
+        Some((position.x + position.y * self.size.x + position.z * self.size.x * self.size.y) as usize)
+    }
+}
+
+/// Reads plain values front to back, failing instead of panicking on truncated data.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < count {
+            return Err(invalid_data("world file is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read<T: bytemuck::Pod>(&mut self) -> io::Result<T> {
+        Ok(bytemuck::pod_read_unaligned(self.take(std::mem::size_of::<T>())?))
+    }
+
+    fn read_slice<T: bytemuck::Pod>(&mut self, count: usize) -> io::Result<Vec<T>> {
+        let length = count
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or_else(|| invalid_data("world file is truncated"))?;
+        let bytes = self.take(length)?;
+        Ok(bytes
+            .chunks_exact(std::mem::size_of::<T>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect())
+    }
+}
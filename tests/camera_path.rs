@@ -0,0 +1,57 @@
+use glam::{vec3, Vec3};
+use project_voxels_v2::camera_path::CameraPath;
+
+/// Four keyframes a second apart along +x, all looking down -z.
+fn straight_path() -> CameraPath {
+    let mut path = CameraPath::new();
+    for index in 0..4 {
+        path.push(index as f32, vec3(index as f32, 0.0, 0.0), Vec3::NEG_Z);
+    }
+    path
+}
+
+fn temp_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}.txt", name, std::process::id()))
+}
+
+#[test]
+fn sample_interpolates_between_keyframes_and_clamps_at_the_ends() {
+    let path = straight_path();
+
+    // Evenly spaced keyframes on a line make the spline linear between the inner ones.
+    let (position, direction) = path.sample(1.5).unwrap();
+    assert!(position.abs_diff_eq(vec3(1.5, 0.0, 0.0), 1.0e-5));
+    assert!(direction.abs_diff_eq(Vec3::NEG_Z, 1.0e-5));
+    assert_eq!(path.sample(-1.0).unwrap().0, Vec3::ZERO);
+    assert_eq!(path.sample(1.0).unwrap().0, vec3(1.0, 0.0, 0.0));
+    assert_eq!(path.sample(10.0).unwrap().0, vec3(3.0, 0.0, 0.0));
+    assert_eq!(CameraPath::new().sample(0.0), None);
+}
+
+#[test]
+fn paths_survive_a_save_and_load() {
+    let mut path = straight_path();
+    path.push(4.5, vec3(1.0, 2.0, -3.0), vec3(1.0, -0.5, 0.25));
+    let file = temp_file("camera_path");
+    path.save(&file).unwrap();
+    let loaded = CameraPath::load(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert_eq!(loaded, path);
+}
+
+#[test]
+fn load_rejects_unordered_times_and_zero_rotations() {
+    let file = temp_file("camera_path_invalid");
+    for contents in [
+        "0 0 0 0 0 0 0 1\n0 1 0 0 0 0 0 1\n",
+        "1 0 0 0 0 0 0 1\n0.5 1 0 0 0 0 0 1\n",
+        "0 0 0 0 0 0 0 0\n",
+        "NaN 0 0 0 0 0 0 1\n",
+    ] {
+        std::fs::write(&file, contents).unwrap();
+        let error = CameraPath::load(&file).expect_err("Invalid path loaded.");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+    std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,35 @@
+use project_voxels_v2::{
+    input::{Binding, GamepadAxis, GamepadButton, InputMap},
+    winit::{event::MouseButton, keyboard::KeyCode},
+};
+
+#[test]
+fn sample_bindings_survive_a_save_and_load() {
+    let bindings = [
+        Binding::Key(KeyCode::KeyW),
+        Binding::Key(KeyCode::Escape),
+        Binding::Key(KeyCode::NumpadEnter),
+        Binding::Mouse(MouseButton::Right),
+        Binding::Mouse(MouseButton::Other(7)),
+        Binding::GamepadButton(GamepadButton::South),
+        Binding::GamepadAxis(GamepadAxis::LeftStickY, false),
+    ];
+    let mut map = InputMap::empty();
+    for binding in bindings {
+        map.bind("action", binding);
+    }
+
+    let path = std::env::temp_dir().join(format!("input_map_{}.cfg", std::process::id()));
+    map.save(&path).unwrap();
+    let loaded = InputMap::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.bindings("action"), bindings);
+}
+
+#[test]
+fn unknown_binding_names_are_rejected() {
+    for name in ["KeyWW", "MouseOther(x)", "MouseOther(70000)", "GamepadLeftStickY"] {
+        assert_eq!(Binding::parse(name), None, "{}", name);
+    }
+}
@@ -0,0 +1,145 @@
+use glam::vec2;
+use project_voxels_v2::{
+    app::{App, Engine},
+    camera::CameraMode,
+    input::Binding,
+    replay::{replay, InputEvent, InputRecording},
+    winit::{event::MouseButton, keyboard::KeyCode},
+};
+
+struct Viewer;
+
+impl App for Viewer {}
+
+/// Applies the events and steps a frame of the given length, the way the engine loop does with real events.
+fn step(engine: &mut Engine, delta_time: f64, events: &[InputEvent]) {
+    for event in events {
+        engine.input.apply(*event);
+    }
+    engine.frame_timer_mut().advance(delta_time);
+    engine.step(&mut Viewer);
+}
+
+/// Drives an engine by hand while recording.
+fn record_session() -> (Engine, InputRecording) {
+    let mut engine = Engine::new();
+    engine.start_recording();
+
+    let frames: [(f64, &[InputEvent]); 4] = [
+        (1.0 / 60.0, &[InputEvent::Press(Binding::Key(KeyCode::KeyW))]),
+        (1.0 / 30.0, &[]),
+        (
+            1.0 / 45.0,
+            &[
+                InputEvent::Press(Binding::Mouse(MouseButton::Right)),
+                InputEvent::MouseMotion(vec2(40.0, -12.5)),
+            ],
+        ),
+        (
+            1.0 / 60.0,
+            &[
+                InputEvent::Release(Binding::Key(KeyCode::KeyW)),
+                InputEvent::Release(Binding::Mouse(MouseButton::Right)),
+            ],
+        ),
+    ];
+    for (delta_time, events) in frames {
+        step(&mut engine, delta_time, events);
+    }
+
+    let recording = engine.input.stop_recording().expect("Recording was started.");
+    (engine, recording)
+}
+
+#[test]
+fn replay_reproduces_the_recorded_session() {
+    let (recorded, recording) = record_session();
+    assert_eq!(recording.frames().len(), 4);
+
+    let mut replayed = Engine::new();
+    replay(&mut Viewer, &mut replayed, &recording);
+
+    assert_ne!(recorded.camera.position(), Engine::new().camera.position());
+    assert_eq!(replayed.camera.position(), recorded.camera.position());
+    assert_eq!(replayed.camera.direction(), recorded.camera.direction());
+}
+
+#[test]
+fn replay_starts_from_the_state_the_recording_started_in() {
+    let mut recorded = Engine::new();
+    recorded.camera.set_mode(CameraMode::Walk);
+    step(&mut recorded, 0.013, &[InputEvent::Press(Binding::Key(KeyCode::KeyW))]);
+    step(&mut recorded, 0.021, &[InputEvent::Press(Binding::Key(KeyCode::Space))]);
+    step(
+        &mut recorded,
+        0.007,
+        &[
+            InputEvent::Press(Binding::Mouse(MouseButton::Right)),
+            InputEvent::MouseMotion(vec2(25.0, 4.0)),
+        ],
+    );
+
+    // W and the look button are still held and the body is mid jump when recording starts.
+    recorded.start_recording();
+    step(&mut recorded, 0.019, &[InputEvent::MouseMotion(vec2(-10.0, 3.0))]);
+    step(&mut recorded, 0.011, &[InputEvent::Release(Binding::Key(KeyCode::Space))]);
+    step(&mut recorded, 0.023, &[InputEvent::Release(Binding::Key(KeyCode::KeyW))]);
+    let recording = recorded.input.stop_recording().expect("Recording was started.");
+
+    let path = std::env::temp_dir().join(format!("input_recording_start_{}.txt", std::process::id()));
+    recording.save(&path).unwrap();
+    let loaded = InputRecording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, recording);
+
+    let mut replayed = Engine::new();
+    replay(&mut Viewer, &mut replayed, &loaded);
+
+    assert_eq!(replayed.camera.mode(), CameraMode::Walk);
+    assert_eq!(replayed.camera.state(), recorded.camera.state());
+    assert_eq!(replayed.frame_timer().accumulator(), recorded.frame_timer().accumulator());
+}
+
+#[test]
+fn recordings_survive_a_save_and_load() {
+    let (_, recording) = record_session();
+    let path = std::env::temp_dir().join(format!("input_recording_{}.txt", std::process::id()));
+    recording.save(&path).unwrap();
+    let loaded = InputRecording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, recording);
+}
+
+/// Counts presses from the input hook, standing in for app logic like voxel editing.
+#[derive(Default)]
+struct PressCounter {
+    presses: Vec<Binding>,
+}
+
+impl App for PressCounter {
+    fn input(&mut self, _engine: &mut Engine, event: &InputEvent) {
+        if let InputEvent::Press(binding) = event {
+            self.presses.push(*binding);
+        }
+    }
+}
+
+#[test]
+fn replay_passes_input_to_the_app() {
+    let mut recorded_app = PressCounter::default();
+    let mut recorded = Engine::new();
+    recorded.start_recording();
+    for binding in [Binding::Key(KeyCode::KeyQ), Binding::Mouse(MouseButton::Left)] {
+        recorded.input.apply(InputEvent::Press(binding));
+        recorded.frame_timer_mut().advance(1.0 / 60.0);
+        recorded.step(&mut recorded_app);
+    }
+    let recording = recorded.input.stop_recording().expect("Recording was started.");
+
+    let mut replayed_app = PressCounter::default();
+    replay(&mut replayed_app, &mut Engine::new(), &recording);
+
+    assert_eq!(recorded_app.presses.len(), 2);
+    assert_eq!(replayed_app.presses, recorded_app.presses);
+}
@@ -0,0 +1,46 @@
+use glam::{ivec3, uvec3, vec3};
+use project_voxels_v2::world::{Material, VoxelWorld};
+
+fn temp_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}.voxw", name, std::process::id()))
+}
+
+#[test]
+fn worlds_survive_a_save_and_load() {
+    let world = VoxelWorld::demo();
+    let path = temp_file("world");
+    world.save(&path).unwrap();
+    let loaded = VoxelWorld::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.size(), world.size());
+    assert_eq!(loaded.origin(), world.origin());
+    assert_eq!(loaded.voxels(), world.voxels());
+    assert_eq!(
+        bytemuck::cast_slice::<Material, u8>(loaded.materials()),
+        bytemuck::cast_slice::<Material, u8>(world.materials())
+    );
+    let water = ivec3(8, 1, 8);
+    assert!(!loaded.is_material_solid(loaded.get(water)));
+}
+
+#[test]
+fn load_rejects_unknown_materials_and_non_finite_origins() {
+    let mut unknown_material = VoxelWorld::new(uvec3(2, 2, 2), vec3(0.0, 0.0, 0.0));
+    unknown_material.set(ivec3(1, 1, 1), 5);
+    let worlds = [
+        unknown_material,
+        VoxelWorld::new(uvec3(2, 2, 2), vec3(f32::NAN, 0.0, 0.0)),
+        VoxelWorld::new(uvec3(2, 2, 2), vec3(0.0, f32::INFINITY, 0.0)),
+    ];
+
+    let path = temp_file("world_invalid");
+    for world in worlds {
+        world.save(&path).unwrap();
+        let error = VoxelWorld::load(&path).err().expect("Invalid world loaded.");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+    std::fs::write(&path, b"VOXW").unwrap();
+    assert_eq!(VoxelWorld::load(&path).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}